@@ -0,0 +1,145 @@
+use crate::clock::Seconds;
+use crate::game::{CellView, Game, Outcome};
+use crate::state::{Flag, Grid};
+
+/// Returns "row R column C" for the cell `idx`, counted from 1 the way they are read out
+pub(crate) fn position(idx: usize, n_rows: usize, n_cols: usize) -> String {
+    match Grid::idx_to_xy(idx, n_rows, n_cols) {
+        Some((row, col)) => format!("row {} column {}", row + 1, col + 1),
+        None => format!("cell {}", idx),
+    }
+}
+
+/// Returns what a screen reader says of the cell `idx`,
+/// like "row 3 column 5, revealed, 2 adjacent mines"
+pub fn cell_label(game: &Game, idx: usize) -> String {
+    let state = match game.cell_view(idx) {
+        CellView::Hidden => String::from("hidden"),
+        CellView::Marked(Flag::Tag) => String::from("flagged"),
+        CellView::Marked(Flag::Question) => String::from("question mark"),
+        CellView::Revealed(0) => String::from("revealed, no adjacent mines"),
+        CellView::Revealed(1) => String::from("revealed, 1 adjacent mine"),
+        CellView::Revealed(count) => format!("revealed, {} adjacent mines", count),
+        CellView::Mine => String::from("mine"),
+        CellView::ExplodedMine => String::from("exploded mine"),
+        CellView::WrongFlag => String::from("flagged, no mine"),
+        CellView::CorrectFlag => String::from("flagged mine"),
+    };
+    let grid = game.grid();
+    format!("{}, {}", position(idx, grid.n_rows, grid.n_cols), state)
+}
+
+/// Returns what to announce after an action had `outcome` on `game`, if anything
+///
+/// A single revealed cell or a marker is left to the label of the cell, only
+/// cascades and the end of the game are worth interrupting the player.
+pub fn announcement(game: &Game, outcome: &Outcome) -> Option<String> {
+    match outcome {
+        Outcome::Revealed(count) if *count > 1 => Some(format!("{} cells revealed.", count)),
+        Outcome::Lost(mine_idx) => Some(format!(
+            "The mine at {} exploded. Game lost in {} seconds.",
+            position(*mine_idx, game.grid().n_rows, game.grid().n_cols),
+            Seconds(game.elapsed_ms())
+        )),
+        Outcome::Won => Some(format!(
+            "Every safe cell is revealed. Game won in {} seconds.",
+            Seconds(game.elapsed_ms())
+        )),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use std::rc::Rc;
+
+    fn new_game(clock: Rc<ManualClock>) -> Game {
+        Game::new(
+            Grid::from_layout(
+                3,
+                3,
+                "* . .
+                 * . .
+                 . . .",
+            ),
+            clock,
+        )
+    }
+
+    #[test]
+    fn test_cell_labels() {
+        let mut game = new_game(Rc::new(ManualClock::default()));
+        assert_eq!(cell_label(&game, 5), "row 2 column 3, hidden");
+        game.dig(4);
+        game.cycle_flag(0, true);
+        game.cycle_flag(1, true);
+        game.cycle_flag(1, true);
+        assert_eq!(
+            cell_label(&game, 4),
+            "row 2 column 2, revealed, 2 adjacent mines"
+        );
+        assert_eq!(cell_label(&game, 0), "row 1 column 1, flagged");
+        assert_eq!(cell_label(&game, 1), "row 1 column 2, question mark");
+        game.dig(2);
+        assert_eq!(
+            cell_label(&game, 2),
+            "row 1 column 3, revealed, no adjacent mines"
+        );
+        assert_eq!(
+            cell_label(&game, 7),
+            "row 3 column 2, revealed, 1 adjacent mine"
+        );
+    }
+
+    #[test]
+    fn test_labels_once_lost() {
+        let mut game = new_game(Rc::new(ManualClock::default()));
+        game.dig(8);
+        game.cycle_flag(0, true);
+        game.cycle_flag(6, false);
+        game.dig(3);
+        assert_eq!(cell_label(&game, 3), "row 2 column 1, exploded mine");
+        assert_eq!(cell_label(&game, 0), "row 1 column 1, flagged mine");
+        assert_eq!(cell_label(&game, 6), "row 3 column 1, flagged, no mine");
+    }
+
+    #[test]
+    fn test_announcements() {
+        let clock = Rc::new(ManualClock::default());
+        let mut game = new_game(clock.clone());
+        let outcome = game.dig(4);
+        assert_eq!(announcement(&game, &outcome), None);
+        let outcome = game.dig(2);
+        assert_eq!(
+            announcement(&game, &outcome),
+            Some(String::from("5 cells revealed."))
+        );
+        let outcome = game.cycle_flag(0, true);
+        assert_eq!(announcement(&game, &outcome), None);
+        clock.advance(2500);
+        let outcome = game.dig(3);
+        assert_eq!(
+            announcement(&game, &outcome),
+            Some(String::from(
+                "The mine at row 2 column 1 exploded. Game lost in 2.500 seconds."
+            ))
+        );
+    }
+
+    #[test]
+    fn test_win_announcement() {
+        let mut game = new_game(Rc::new(ManualClock::default()));
+        game.dig(2);
+        game.dig(4);
+        let outcome = game.dig(6);
+        assert_eq!(outcome, Outcome::Won);
+        assert_eq!(
+            announcement(&game, &outcome),
+            Some(String::from(
+                "Every safe cell is revealed. Game won in 0.000 seconds."
+            ))
+        );
+    }
+}
@@ -0,0 +1,126 @@
+use std::cell::Cell;
+use std::fmt::{Display, Formatter};
+
+/// A source of monotonic timestamps, in milliseconds since an arbitrary origin
+///
+/// The web front-end reads `performance.now()`, while tests and tools drive a
+/// `ManualClock` by hand.
+pub trait Clock {
+    fn now_ms(&self) -> u64;
+}
+
+/// A clock that only moves when told to
+#[derive(Default, Debug)]
+pub struct ManualClock {
+    now_ms: Cell<u64>,
+}
+
+impl ManualClock {
+    pub fn advance(&self, ms: u64) {
+        self.now_ms.set(self.now_ms.get() + ms);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.get()
+    }
+}
+
+/// Time accumulated over the periods between `start` and `stop`
+#[derive(Default, Debug)]
+pub struct Stopwatch {
+    accumulated_ms: u64,
+    started_at: Option<u64>,
+}
+
+impl Stopwatch {
+    /// Returns a stopped stopwatch that already accumulated `elapsed_ms`
+    pub fn from_elapsed(elapsed_ms: u64) -> Self {
+        Stopwatch {
+            accumulated_ms: elapsed_ms,
+            started_at: None,
+        }
+    }
+
+    /// Starts a period at `now_ms`, unless one is already running
+    pub fn start(&mut self, now_ms: u64) {
+        self.started_at.get_or_insert(now_ms);
+    }
+
+    /// Ends the running period at `now_ms`, if there is one
+    pub fn stop(&mut self, now_ms: u64) {
+        if let Some(started_at) = self.started_at.take() {
+            self.accumulated_ms += now_ms.saturating_sub(started_at);
+        }
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.started_at.is_some()
+    }
+
+    /// Returns the time accumulated so far, counting the running period up to `now_ms`
+    pub fn elapsed_ms(&self, now_ms: u64) -> u64 {
+        let running_ms = self
+            .started_at
+            .map_or(0, |started_at| now_ms.saturating_sub(started_at));
+        self.accumulated_ms + running_ms
+    }
+}
+
+/// Displays a duration in milliseconds as seconds, with as many decimals as the
+/// precision of the format asks for, up to the default of three
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct Seconds(pub u64);
+
+impl Display for Seconds {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let precision = f.precision().unwrap_or(3).min(3);
+        let whole = self.0 / 1000;
+        if precision == 0 {
+            return write!(f, "{}", whole);
+        }
+        // truncate rather than round, so that the display never runs ahead of time
+        let fraction = (self.0 % 1000) / 10_u64.pow(3 - precision as u32);
+        write!(f, "{}.{:0width$}", whole, fraction, width = precision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stopwatch_periods() {
+        let mut stopwatch = Stopwatch::default();
+        assert_eq!(stopwatch.elapsed_ms(500), 0);
+        stopwatch.start(1000);
+        assert_eq!(stopwatch.elapsed_ms(1250), 250);
+        // starting again does not restart the running period
+        stopwatch.start(1500);
+        stopwatch.stop(2000);
+        assert!(!stopwatch.is_running());
+        assert_eq!(stopwatch.elapsed_ms(9000), 1000);
+        stopwatch.stop(9500);
+        stopwatch.start(10_000);
+        assert_eq!(stopwatch.elapsed_ms(10_001), 1001);
+    }
+
+    #[test]
+    fn test_manual_clock() {
+        let clock = ManualClock::default();
+        assert_eq!(clock.now_ms(), 0);
+        clock.advance(1500);
+        clock.advance(7);
+        assert_eq!(clock.now_ms(), 1507);
+    }
+
+    #[test]
+    fn test_seconds_display() {
+        assert_eq!(Seconds(0).to_string(), "0.000");
+        assert_eq!(Seconds(999).to_string(), "0.999");
+        assert_eq!(format!("{:.1}", Seconds(1999)), "1.9");
+        assert_eq!(format!("{:.2}", Seconds(61_005)), "61.00");
+        assert_eq!(format!("{:.0}", Seconds(61_999)), "61");
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt::{Display, Formatter};
+
+/// Dimensions and number of mines of a minesweeper grid
+///
+/// A configuration can only be built through `GameConfig::new` or the presets,
+/// so it always describes a playable grid.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct GameConfig {
+    n_rows: usize,
+    n_cols: usize,
+    n_mines: usize,
+}
+
+/// Reasons for a configuration to be rejected
+#[derive(Eq, PartialEq, Debug)]
+pub enum ConfigError {
+    /// The grid has no rows or no columns
+    EmptyGrid,
+    /// There are not fewer mines than cells, so no cell is left to dig
+    TooManyMines { n_mines: usize, n_cells: usize },
+    /// The grid has more rows or columns than a page can play
+    TooLarge { max_rows: usize, max_cols: usize },
+}
+
+impl Display for ConfigError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::EmptyGrid => write!(f, "The grid needs at least one row and one column."),
+            ConfigError::TooManyMines { n_mines, n_cells } => write!(
+                f,
+                "{} mines do not fit in {} cells, at most {} are allowed.",
+                n_mines,
+                n_cells,
+                n_cells - 1
+            ),
+            ConfigError::TooLarge { max_rows, max_cols } => write!(
+                f,
+                "The grid can have at most {} rows and {} columns.",
+                max_rows, max_cols
+            ),
+        }
+    }
+}
+
+impl GameConfig {
+    pub const BEGINNER: GameConfig = GameConfig {
+        n_rows: 9,
+        n_cols: 9,
+        n_mines: 10,
+    };
+    pub const INTERMEDIATE: GameConfig = GameConfig {
+        n_rows: 16,
+        n_cols: 16,
+        n_mines: 40,
+    };
+    pub const EXPERT: GameConfig = GameConfig {
+        n_rows: 16,
+        n_cols: 30,
+        n_mines: 99,
+    };
+    /// Most rows of a grid, which keeps its generation and measures quick
+    pub const MAX_ROWS: usize = 100;
+    /// Most columns of a grid
+    pub const MAX_COLS: usize = 100;
+
+    /// Returns a configuration, or an error if the grid would not be playable
+    ///
+    /// # Arguments
+    /// * `n_rows` - Number of rows in the grid, at most `MAX_ROWS`
+    /// * `n_cols` - Number of columns in the grid, at most `MAX_COLS`
+    /// * `n_mines` - Number of mines in the grid, which must leave at least one safe cell
+    pub fn new(n_rows: usize, n_cols: usize, n_mines: usize) -> Result<Self, ConfigError> {
+        if n_rows == 0 || n_cols == 0 {
+            return Err(ConfigError::EmptyGrid);
+        }
+        let n_cells = n_rows
+            .checked_mul(n_cols)
+            .filter(|_| n_rows <= Self::MAX_ROWS && n_cols <= Self::MAX_COLS)
+            .ok_or(ConfigError::TooLarge {
+                max_rows: Self::MAX_ROWS,
+                max_cols: Self::MAX_COLS,
+            })?;
+        if n_mines >= n_cells {
+            return Err(ConfigError::TooManyMines { n_mines, n_cells });
+        }
+        Ok(GameConfig {
+            n_rows,
+            n_cols,
+            n_mines,
+        })
+    }
+
+    pub fn n_rows(&self) -> usize {
+        self.n_rows
+    }
+
+    pub fn n_cols(&self) -> usize {
+        self.n_cols
+    }
+
+    pub fn n_mines(&self) -> usize {
+        self.n_mines
+    }
+
+    pub fn n_cells(&self) -> usize {
+        self.n_rows * self.n_cols
+    }
+}
+
+impl Display for GameConfig {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}, {} mines", self.n_rows, self.n_cols, self.n_mines)
+    }
+}
+
+/// The standard difficulties of minesweeper
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Preset {
+    Beginner,
+    Intermediate,
+    Expert,
+}
+
+impl Preset {
+    pub const ALL: [Preset; 3] = [Preset::Beginner, Preset::Intermediate, Preset::Expert];
+
+    pub fn config(&self) -> GameConfig {
+        match self {
+            Preset::Beginner => GameConfig::BEGINNER,
+            Preset::Intermediate => GameConfig::INTERMEDIATE,
+            Preset::Expert => GameConfig::EXPERT,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Preset::Beginner => "Beginner",
+            Preset::Intermediate => "Intermediate",
+            Preset::Expert => "Expert",
+        }
+    }
+
+    /// Returns the preset matching `config`, if there is one
+    pub fn of(config: GameConfig) -> Option<Preset> {
+        Self::ALL
+            .into_iter()
+            .find(|preset| preset.config() == config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_config() {
+        let config = GameConfig::new(16, 30, 99).unwrap();
+        assert_eq!(config, GameConfig::EXPERT);
+        assert_eq!(config.n_cells(), 480);
+        assert_eq!(GameConfig::new(1, 2, 1).unwrap().n_mines(), 1);
+        assert_eq!(GameConfig::new(3, 3, 0).unwrap().n_mines(), 0);
+    }
+
+    #[test]
+    fn test_empty_grid_is_rejected() {
+        assert_eq!(GameConfig::new(0, 10, 0), Err(ConfigError::EmptyGrid));
+        assert_eq!(GameConfig::new(10, 0, 0), Err(ConfigError::EmptyGrid));
+    }
+
+    #[test]
+    fn test_too_many_mines_are_rejected() {
+        assert_eq!(
+            GameConfig::new(3, 3, 9),
+            Err(ConfigError::TooManyMines {
+                n_mines: 9,
+                n_cells: 9
+            })
+        );
+        assert!(GameConfig::new(3, 3, 100).is_err());
+        assert!(GameConfig::new(3, 3, 8).is_ok());
+    }
+
+    #[test]
+    fn test_too_large_grids_are_rejected() {
+        let too_large = Err(ConfigError::TooLarge {
+            max_rows: GameConfig::MAX_ROWS,
+            max_cols: GameConfig::MAX_COLS,
+        });
+        assert_eq!(GameConfig::new(101, 10, 10), too_large);
+        assert_eq!(GameConfig::new(10, 101, 10), too_large);
+        // the number of cells would overflow
+        assert_eq!(GameConfig::new(usize::MAX, 2, 1), too_large);
+        assert_eq!(GameConfig::new(1 << 40, 1 << 40, 1), too_large);
+        assert_eq!(GameConfig::new(100, 100, 9999).unwrap().n_cells(), 10_000);
+    }
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(Preset::of(GameConfig::BEGINNER), Some(Preset::Beginner));
+        assert_eq!(
+            Preset::of(GameConfig::INTERMEDIATE),
+            Some(Preset::Intermediate)
+        );
+        assert_eq!(Preset::of(GameConfig::EXPERT), Some(Preset::Expert));
+        assert_eq!(Preset::of(GameConfig::new(9, 9, 11).unwrap()), None);
+        for preset in Preset::ALL {
+            let config = preset.config();
+            assert_eq!(
+                GameConfig::new(config.n_rows(), config.n_cols(), config.n_mines()),
+                Ok(config)
+            );
+        }
+    }
+}
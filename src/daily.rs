@@ -0,0 +1,348 @@
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::game::GameResult;
+use crate::save::{read_envelope, write_envelope, LoadError};
+use crate::state::{GenerationPolicy, Grid};
+
+/// Version written by `DailyLog::to_json`
+pub const DAILY_LOG_VERSION: u32 = 1;
+
+/// A day of the proleptic Gregorian calendar
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct CalendarDate {
+    pub year: i32,
+    /// From 1 for January to 12 for December
+    pub month: u32,
+    /// From 1
+    pub day: u32,
+}
+
+impl CalendarDate {
+    /// Returns the number of days from 1970-01-01 to this date, negative before it
+    pub fn days_since_epoch(&self) -> i64 {
+        // see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = self.year as i64 - if self.month <= 2 { 1 } else { 0 };
+        let era = year.div_euclid(400);
+        let year_of_era = year - era * 400;
+        let month_from_march = (self.month as i64 + 9) % 12;
+        let day_of_year = (153 * month_from_march + 2) / 5 + self.day as i64 - 1;
+        let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+        era * 146_097 + day_of_era - 719_468
+    }
+}
+
+impl Display for CalendarDate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+/// The board of the day, the same for every player
+///
+/// Its mines are laid out from a seed derived from the date, as if the first dig
+/// was on `start`, so that the board does not depend on where the player digs first.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct DailyChallenge {
+    pub date: CalendarDate,
+    /// The cell every player should dig first, which opens a region
+    pub start: usize,
+}
+
+impl DailyChallenge {
+    pub const CONFIG: GameConfig = GameConfig::INTERMEDIATE;
+    pub const POLICY: GenerationPolicy = GenerationPolicy::NoGuess;
+
+    pub fn new(date: CalendarDate) -> Self {
+        let center = (Self::CONFIG.n_rows() / 2, Self::CONFIG.n_cols() / 2);
+        DailyChallenge {
+            date,
+            start: Grid::xy_to_idx(center, Self::CONFIG.n_rows(), Self::CONFIG.n_cols())
+                .expect("the center is in the grid"),
+        }
+    }
+
+    pub fn day(&self) -> i64 {
+        self.date.days_since_epoch()
+    }
+
+    /// Returns the seed of the day, mixed so that consecutive days get unrelated boards
+    pub fn seed(&self) -> u64 {
+        // SplitMix64, which gives the same result on every platform
+        let mut z = (self.day() as u64).wrapping_add(0x9e37_79b9_7f4a_7c15);
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Returns the board of the day, with its mines placed
+    pub fn grid(&self) -> Grid {
+        let mut grid = Grid::from_seed(Self::CONFIG, Self::POLICY, self.seed());
+        grid.prepare_dig(self.start);
+        grid
+    }
+}
+
+/// The scored attempt of a day, which is over once it has a time
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct DailyEntry {
+    pub day: i64,
+    pub won: bool,
+    pub elapsed_ms: Option<u64>,
+}
+
+/// Reasons for an attempt at a daily challenge not to be scored
+#[derive(Eq, PartialEq, Debug)]
+pub enum DailyError {
+    /// The challenge of this day already had its scored attempt
+    AlreadyPlayed,
+    /// The attempt being finished was never started
+    NotStarted,
+    /// The attempt being finished took hints, so it keeps no time
+    Hinted,
+}
+
+impl Display for DailyError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DailyError::AlreadyPlayed => write!(f, "This daily challenge was already played."),
+            DailyError::NotStarted => write!(f, "This daily challenge was not started."),
+            DailyError::Hinted => write!(f, "This daily challenge was played with hints."),
+        }
+    }
+}
+
+/// The scored attempts at daily challenges, one per day at most
+///
+/// An attempt counts from its first dig, so that leaving a bad start
+/// does not give a second chance: it stays lost unless it is finished.
+#[derive(Eq, PartialEq, Default, Debug)]
+pub struct DailyLog {
+    entries: Vec<DailyEntry>,
+}
+
+/// The log as stored, next to its version
+#[derive(Serialize, Deserialize)]
+struct StoredEntries<T> {
+    entries: T,
+}
+
+impl DailyLog {
+    pub fn entry(&self, day: i64) -> Option<&DailyEntry> {
+        self.entries.iter().find(|entry| entry.day == day)
+    }
+
+    /// Starts the scored attempt of `day`
+    pub fn start(&mut self, day: i64) -> Result<(), DailyError> {
+        if self.entry(day).is_some() {
+            return Err(DailyError::AlreadyPlayed);
+        }
+        self.entries.push(DailyEntry {
+            day,
+            won: false,
+            elapsed_ms: None,
+        });
+        Ok(())
+    }
+
+    /// Records how the scored attempt of `day` ended. An attempt that took hints
+    /// is rejected, and stays unfinished like an abandoned one.
+    pub fn finish(&mut self, day: i64, result: &GameResult) -> Result<(), DailyError> {
+        let entry = self
+            .entries
+            .iter_mut()
+            .find(|entry| entry.day == day)
+            .ok_or(DailyError::NotStarted)?;
+        if entry.elapsed_ms.is_some() {
+            return Err(DailyError::AlreadyPlayed);
+        }
+        if result.hints > 0 {
+            return Err(DailyError::Hinted);
+        }
+        entry.won = result.won;
+        entry.elapsed_ms = Some(result.elapsed_ms);
+        Ok(())
+    }
+
+    /// Returns the number of days in a row whose challenge was won, up to `today`,
+    /// or up to yesterday while today's challenge is still to be won
+    pub fn streak(&self, today: i64) -> usize {
+        let won = |day: i64| self.entry(day).is_some_and(|entry| entry.won);
+        let last_day = if won(today) { today } else { today - 1 };
+        (0..).take_while(|offset| won(last_day - offset)).count()
+    }
+
+    /// Returns the log as versioned JSON, to be read back by `from_json`
+    pub fn to_json(&self) -> String {
+        write_envelope(
+            DAILY_LOG_VERSION,
+            StoredEntries {
+                entries: &self.entries,
+            },
+        )
+    }
+
+    /// Reads a log written by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let (_, StoredEntries { entries }): (u32, StoredEntries<Vec<DailyEntry>>) =
+            read_envelope(json, DAILY_LOG_VERSION..=DAILY_LOG_VERSION)?;
+        let days: HashSet<i64> = entries.iter().map(|entry| entry.day).collect();
+        if days.len() != entries.len() {
+            return Err(LoadError::Inconsistent("a day has several attempts"));
+        }
+        Ok(DailyLog { entries })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Clicks;
+    use crate::replay::ReplayCode;
+    use crate::solver;
+    use crate::state::CellData;
+
+    fn date(year: i32, month: u32, day: u32) -> CalendarDate {
+        CalendarDate { year, month, day }
+    }
+
+    fn result(won: bool, elapsed_ms: u64) -> GameResult {
+        GameResult {
+            won,
+            config: DailyChallenge::CONFIG,
+            replay: ReplayCode {
+                config: DailyChallenge::CONFIG,
+                policy: DailyChallenge::POLICY,
+                seed: 0,
+                start: Some(0),
+            },
+            elapsed_ms,
+            clicks: Clicks {
+                left: 1,
+                right: 0,
+                chord: 0,
+            },
+            hints: 0,
+            three_bv: 1,
+            zini: 1,
+        }
+    }
+
+    #[test]
+    fn test_days_since_epoch() {
+        assert_eq!(date(1970, 1, 1).days_since_epoch(), 0);
+        assert_eq!(date(1969, 12, 31).days_since_epoch(), -1);
+        assert_eq!(date(2000, 3, 1).days_since_epoch(), 11_017);
+        assert_eq!(date(2024, 2, 29).days_since_epoch(), 19_782);
+        assert_eq!(date(2024, 3, 1).days_since_epoch(), 19_783);
+        assert_eq!(date(2024, 3, 1).to_string(), "2024-03-01");
+    }
+
+    #[test]
+    fn test_same_day_same_board() {
+        let challenge = DailyChallenge::new(date(2024, 5, 17));
+        assert_eq!(challenge.grid().to_string(), challenge.grid().to_string());
+        let next_day = DailyChallenge::new(date(2024, 5, 18));
+        assert_ne!(challenge.seed(), next_day.seed());
+        assert_ne!(challenge.grid().to_string(), next_day.grid().to_string());
+    }
+
+    #[test]
+    fn test_board_opens_from_the_start() {
+        for day in 1..=5 {
+            let challenge = DailyChallenge::new(date(2024, 1, day));
+            let grid = challenge.grid();
+            assert!(grid.mines_placed());
+            assert!(grid.grid_vec[challenge.start].data == CellData::MineNeighbor(0));
+            assert!(solver::is_solvable(&grid, challenge.start));
+        }
+    }
+
+    #[test]
+    fn test_one_scored_attempt_per_day() {
+        let mut log = DailyLog::default();
+        assert_eq!(
+            log.finish(10, &result(true, 1000)),
+            Err(DailyError::NotStarted)
+        );
+        assert_eq!(log.start(10), Ok(()));
+        assert_eq!(log.start(10), Err(DailyError::AlreadyPlayed));
+        assert_eq!(log.finish(10, &result(false, 1000)), Ok(()));
+        assert_eq!(
+            log.finish(10, &result(true, 500)),
+            Err(DailyError::AlreadyPlayed)
+        );
+        assert_eq!(
+            log.entry(10),
+            Some(&DailyEntry {
+                day: 10,
+                won: false,
+                elapsed_ms: Some(1000)
+            })
+        );
+    }
+
+    #[test]
+    fn test_hinted_attempt_keeps_no_time() {
+        let mut log = DailyLog::default();
+        log.start(10).unwrap();
+        let hinted = GameResult {
+            hints: 1,
+            ..result(true, 1000)
+        };
+        assert_eq!(log.finish(10, &hinted), Err(DailyError::Hinted));
+        assert_eq!(
+            log.entry(10),
+            Some(&DailyEntry {
+                day: 10,
+                won: false,
+                elapsed_ms: None
+            })
+        );
+        assert_eq!(log.streak(10), 0);
+    }
+
+    #[test]
+    fn test_streak() {
+        let mut log = DailyLog::default();
+        for (day, won) in [
+            (1, true),
+            (3, true),
+            (4, true),
+            (5, false),
+            (6, true),
+            (7, true),
+        ] {
+            log.start(day).unwrap();
+            log.finish(day, &result(won, 1000)).unwrap();
+        }
+        assert_eq!(log.streak(7), 2);
+        // today is still to be played
+        assert_eq!(log.streak(8), 2);
+        assert_eq!(log.streak(9), 0);
+        assert_eq!(log.streak(4), 2);
+        assert_eq!(log.streak(5), 2);
+        // an attempt that was left unfinished breaks the streak
+        log.start(8).unwrap();
+        assert_eq!(log.streak(9), 0);
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut log = DailyLog::default();
+        log.start(3).unwrap();
+        log.finish(3, &result(true, 1234)).unwrap();
+        log.start(4).unwrap();
+        assert_eq!(DailyLog::from_json(&log.to_json()), Ok(log));
+        let twice = "{\"version\":1,\"entries\":[\
+            {\"day\":3,\"won\":true,\"elapsed_ms\":1},\
+            {\"day\":3,\"won\":false,\"elapsed_ms\":null}]}";
+        assert!(matches!(
+            DailyLog::from_json(twice),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+}
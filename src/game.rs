@@ -0,0 +1,805 @@
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+use crate::clock::{Clock, Stopwatch};
+use crate::config::GameConfig;
+use crate::hint::{self, Hint};
+use crate::replay::ReplayCode;
+use crate::solver::Board;
+use crate::state::{CellData, ChordOutcome, DigOutcome, Flag, Grid};
+
+/// Progress of a game
+///
+/// A game starts as `NotStarted`, is `Playing` from the first dig on, can be
+/// `Paused` and resumed while playing, and ends as either `Won` or `Lost`.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum GameStatus {
+    NotStarted,
+    Playing,
+    Paused,
+    Lost,
+    Won,
+}
+
+impl GameStatus {
+    /// Returns whether the lifecycle allows going from this status to `to`
+    pub fn can_become(&self, to: GameStatus) -> bool {
+        matches!(
+            (self, to),
+            (GameStatus::NotStarted, GameStatus::Playing)
+                | (GameStatus::Playing, GameStatus::Paused)
+                | (GameStatus::Paused, GameStatus::Playing)
+                | (GameStatus::Playing, GameStatus::Won)
+                | (GameStatus::Playing, GameStatus::Lost)
+        )
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self, GameStatus::Won | GameStatus::Lost)
+    }
+}
+
+/// A change of status the lifecycle does not allow
+#[derive(Eq, PartialEq, Debug)]
+pub struct TransitionError {
+    pub from: GameStatus,
+    pub to: GameStatus,
+}
+
+impl Display for TransitionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "A game cannot go from {:?} to {:?}.", self.from, self.to)
+    }
+}
+
+/// What an action did to the game
+#[derive(Eq, PartialEq, Debug)]
+pub enum Outcome {
+    /// Nothing changed, because the game is paused or over, or the cell does not allow the action
+    Ignored,
+    /// This many cells were revealed, and the game goes on
+    Revealed(usize),
+    /// The marker of the cell changed to this one
+    Marked(Option<Flag>),
+    /// The mine at this index exploded, and the game is lost
+    Lost(usize),
+    /// The last safe cell was revealed, and the game is won
+    Won,
+}
+
+/// What the player gets to see of a cell
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum CellView {
+    /// The cell is not revealed and carries no marker
+    Hidden,
+    /// The cell is not revealed and carries this marker
+    Marked(Flag),
+    /// The cell is revealed and has this many mined neighbors
+    Revealed(usize),
+    /// Once the game is lost, a mine that was not flagged
+    Mine,
+    /// Once the game is lost, a mine that was revealed and exploded
+    ExplodedMine,
+    /// Once the game is lost, a flag put on a cell without a mine
+    WrongFlag,
+    /// Once the game is over, a mine that was flagged. Winning flags every mine.
+    CorrectFlag,
+}
+
+/// Actions the player made on cells, by the click they take with a mouse
+#[derive(Eq, PartialEq, Clone, Copy, Default, Debug)]
+pub struct Clicks {
+    /// Digs
+    pub left: usize,
+    /// Marker changes
+    pub right: usize,
+    pub chord: usize,
+}
+
+impl Clicks {
+    pub fn total(&self) -> usize {
+        self.left + self.right + self.chord
+    }
+}
+
+/// Returns the 3BV cleared per second, the speed of a game regardless of its board
+pub fn three_bv_per_second(three_bv: usize, elapsed_ms: u64) -> f64 {
+    three_bv as f64 * 1000.0 / elapsed_ms.max(1) as f64
+}
+
+/// Returns the 3BV divided by the clicks it took, 1 for a game played without a
+/// wasted click, or `None` without any click
+pub fn efficiency(three_bv: usize, clicks: usize) -> Option<f64> {
+    if clicks == 0 {
+        return None;
+    }
+    Some(three_bv as f64 / clicks as f64)
+}
+
+/// How a finished game went, enough to replay its board and rank its time
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct GameResult {
+    pub won: bool,
+    pub config: GameConfig,
+    /// Lays the board out again, see `crate::replay`
+    pub replay: ReplayCode,
+    pub elapsed_ms: u64,
+    pub clicks: Clicks,
+    /// Hints asked for, any of which keeps the time out of the best times
+    pub hints: usize,
+    /// 3BV of the whole board, see `Grid::three_bv`, even when the game was lost,
+    /// which is why the speed and efficiency of a lost game are not measured
+    pub three_bv: usize,
+    /// ZiNi of the whole board, see `Grid::zini`, to compare the clicks with
+    pub zini: usize,
+}
+
+impl GameResult {
+    /// Returns the 3BV cleared per second of a won game, `None` once lost
+    pub fn three_bv_per_second(&self) -> Option<f64> {
+        self.won
+            .then(|| three_bv_per_second(self.three_bv, self.elapsed_ms))
+    }
+
+    /// Returns the efficiency of a won game, `None` once lost or without any click
+    pub fn efficiency(&self) -> Option<f64> {
+        efficiency(self.three_bv, self.clicks.total()).filter(|_| self.won)
+    }
+}
+
+/// A game of minesweeper: a grid, the rules applied to it, and the time spent on it
+///
+/// Actions on cells are applied through `dig`, `chord` and `cycle_flag`, which
+/// report their effect as an `Outcome`. Nothing in here depends on a browser:
+/// time is read from the `Clock` given to `Game::new` whenever the status changes.
+pub struct Game {
+    grid: Grid,
+    status: GameStatus,
+    clock: Rc<dyn Clock>,
+    stopwatch: Stopwatch,
+    clicks: Clicks,
+    hints: usize,
+}
+
+impl Game {
+    pub fn new(grid: Grid, clock: Rc<dyn Clock>) -> Self {
+        Game {
+            grid,
+            status: GameStatus::NotStarted,
+            clock,
+            stopwatch: Stopwatch::default(),
+            clicks: Clicks::default(),
+            hints: 0,
+        }
+    }
+
+    /// Puts a saved game back together, see `crate::save`
+    ///
+    /// A game saved while in progress comes back paused, with its time stopped
+    /// until the player resumes it.
+    pub(crate) fn restore(
+        grid: Grid,
+        status: GameStatus,
+        elapsed_ms: u64,
+        clicks: Clicks,
+        hints: usize,
+        clock: Rc<dyn Clock>,
+    ) -> Self {
+        Game {
+            grid,
+            status: match status {
+                GameStatus::Playing => GameStatus::Paused,
+                status => status,
+            },
+            clock,
+            stopwatch: Stopwatch::from_elapsed(elapsed_ms),
+            clicks,
+            hints,
+        }
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    pub fn status(&self) -> GameStatus {
+        self.status
+    }
+
+    /// Returns the time spent playing, which only runs while the status is `Playing`
+    pub fn elapsed_ms(&self) -> u64 {
+        self.stopwatch.elapsed_ms(self.clock.now_ms())
+    }
+
+    /// Returns the actions the player made on cells, whether or not they changed
+    /// anything, leaving out the ones made while paused or once over
+    pub fn clicks(&self) -> Clicks {
+        self.clicks
+    }
+
+    pub fn hints(&self) -> usize {
+        self.hints
+    }
+
+    /// Returns how the game went, once it is over
+    pub fn result(&self) -> Option<GameResult> {
+        if !self.status.is_over() {
+            return None;
+        }
+        Some(GameResult {
+            won: self.status == GameStatus::Won,
+            config: self.grid.config(),
+            replay: ReplayCode::of(&self.grid).expect("the mines of a finished game are laid out"),
+            elapsed_ms: self.elapsed_ms(),
+            clicks: self.clicks,
+            hints: self.hints,
+            three_bv: self.grid.three_bv(),
+            zini: self.grid.zini(),
+        })
+    }
+
+    /// Classifies a cell for display, revealing the mines and the mistakes once
+    /// the game is over
+    pub fn cell_view(&self, idx: usize) -> CellView {
+        let cell = &self.grid.grid_vec[idx];
+        let is_mine = cell.data == CellData::Mine;
+        let is_tagged = cell.flag == Some(Flag::Tag);
+        match (self.status, &cell.data) {
+            (_, CellData::MineNeighbor(count)) if cell.is_clicked => CellView::Revealed(*count),
+            (GameStatus::Lost, _) if cell.is_clicked => CellView::ExplodedMine,
+            (GameStatus::Lost, _) | (GameStatus::Won, _) if is_mine && is_tagged => {
+                CellView::CorrectFlag
+            }
+            (GameStatus::Lost, _) if is_mine => CellView::Mine,
+            (GameStatus::Lost, _) if is_tagged => CellView::WrongFlag,
+            _ => match cell.flag {
+                Some(flag) => CellView::Marked(flag),
+                None => CellView::Hidden,
+            },
+        }
+    }
+
+    /// Returns a cell to dig, found from what the player sees of the grid, and
+    /// counts it. Hints are only given while playing.
+    pub fn hint(&mut self) -> Option<Hint> {
+        if self.status != GameStatus::Playing {
+            return None;
+        }
+        let hint = hint::hint(&Board::from_grid(&self.grid))?;
+        self.hints += 1;
+        Some(hint)
+    }
+
+    /// Pauses a game in progress, which then ignores every action
+    pub fn pause(&mut self) -> Result<(), TransitionError> {
+        self.transition(GameStatus::Paused)
+    }
+
+    /// Resumes a paused game. Only the first dig starts a game that is not started.
+    pub fn resume(&mut self) -> Result<(), TransitionError> {
+        if self.status != GameStatus::Paused {
+            return Err(TransitionError {
+                from: self.status,
+                to: GameStatus::Playing,
+            });
+        }
+        self.transition(GameStatus::Playing)
+    }
+
+    fn transition(&mut self, to: GameStatus) -> Result<(), TransitionError> {
+        if !self.status.can_become(to) {
+            return Err(TransitionError {
+                from: self.status,
+                to,
+            });
+        }
+        self.status = to;
+        let now_ms = self.clock.now_ms();
+        if to == GameStatus::Playing {
+            self.stopwatch.start(now_ms);
+        } else {
+            self.stopwatch.stop(now_ms);
+        }
+        Ok(())
+    }
+
+    /// Digs a cell, placing the mines first and starting the game if it is the first dig
+    pub fn dig(&mut self, idx: usize) -> Outcome {
+        if self.status != GameStatus::Playing && self.status != GameStatus::NotStarted {
+            return Outcome::Ignored;
+        }
+        self.clicks.left += 1;
+        let outcome = match self.grid.dig(idx) {
+            DigOutcome::Ignored => return Outcome::Ignored,
+            DigOutcome::Exploded => Outcome::Lost(idx),
+            DigOutcome::Revealed(count) => Outcome::Revealed(count),
+        };
+        if self.status == GameStatus::NotStarted {
+            self.transition(GameStatus::Playing)
+                .expect("a game that is not started can start");
+        }
+        self.settle(outcome)
+    }
+
+    /// Reveals the unflagged neighbors of a revealed number surrounded by as many flags
+    pub fn chord(&mut self, idx: usize) -> Outcome {
+        if self.status != GameStatus::Playing {
+            return Outcome::Ignored;
+        }
+        self.clicks.chord += 1;
+        let outcome = match self.grid.chord(idx) {
+            ChordOutcome::Ignored => Outcome::Ignored,
+            ChordOutcome::Exploded(mine_idx) => Outcome::Lost(mine_idx),
+            ChordOutcome::Revealed(count) => Outcome::Revealed(count),
+        };
+        self.settle(outcome)
+    }
+
+    /// Moves the marker of a cell to the next one, see `Grid::cycle_flag`.
+    /// Cells can be marked before the first dig without starting the game.
+    /// Right clicks on revealed cells, which cannot be marked, are not counted.
+    pub fn cycle_flag(&mut self, idx: usize, allow_question: bool) -> Outcome {
+        if self.status != GameStatus::Playing && self.status != GameStatus::NotStarted {
+            return Outcome::Ignored;
+        }
+        if self.grid.grid_vec[idx].is_clicked {
+            return Outcome::Ignored;
+        }
+        self.clicks.right += 1;
+        if !self.grid.cycle_flag(idx, allow_question) {
+            return Outcome::Ignored;
+        }
+        Outcome::Marked(self.grid.grid_vec[idx].flag)
+    }
+
+    /// Updates the status from the state of the grid after an action.
+    /// A revealed cell turns into `Outcome::Won` when it leaves no safe cell to reveal.
+    fn settle(&mut self, outcome: Outcome) -> Outcome {
+        debug_assert!(
+            self.grid
+                .grid_vec
+                .iter()
+                .all(|c| !c.is_clicked || c.flag.is_none()),
+            "revealed cells never carry a marker"
+        );
+        let exploded = self.grid.is_exploded();
+        let cleared = self.grid.is_cleared();
+        debug_assert!(
+            exploded == matches!(outcome, Outcome::Lost(_)),
+            "only the action that revealed a mine loses the game"
+        );
+        if exploded {
+            self.transition(GameStatus::Lost)
+                .expect("only a game in progress explodes");
+            outcome
+        } else if cleared {
+            debug_assert!(matches!(outcome, Outcome::Revealed(_)));
+            self.transition(GameStatus::Won)
+                .expect("only a game in progress is won");
+            // every cell left is a mine, so they all get flagged
+            for cell in self.grid.grid_vec.iter_mut().filter(|c| !c.is_clicked) {
+                cell.flag = Some(Flag::Tag);
+            }
+            Outcome::Won
+        } else {
+            outcome
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+
+    fn new_game(grid: Grid) -> Game {
+        Game::new(grid, Rc::new(ManualClock::default()))
+    }
+
+    /// ```text
+    /// * 1 0
+    /// 1 1 0
+    /// 0 0 0
+    /// ```
+    fn corner_grid() -> Grid {
+        Grid::from_layout(
+            3,
+            3,
+            "* . .
+             . . .
+             . . .",
+        )
+    }
+
+    fn corner_game() -> Game {
+        new_game(corner_grid())
+    }
+
+    #[test]
+    fn test_dig_to_win() {
+        let mut game = corner_game();
+        assert_eq!(game.dig(1), Outcome::Revealed(1));
+        assert_eq!(game.status(), GameStatus::Playing);
+        assert_eq!(game.dig(8), Outcome::Won);
+        assert_eq!(game.status(), GameStatus::Won);
+    }
+
+    #[test]
+    fn test_dig_a_mine() {
+        let mut game = corner_game();
+        assert_eq!(game.dig(0), Outcome::Lost(0));
+        assert_eq!(game.status(), GameStatus::Lost);
+        // the 3BV of the board was not cleared, so it measures nothing of the game
+        let result = game.result().unwrap();
+        assert_eq!(result.three_bv, 1);
+        assert_eq!(result.three_bv_per_second(), None);
+        assert_eq!(result.efficiency(), None);
+    }
+
+    #[test]
+    fn test_chord_to_win() {
+        let mut game = corner_game();
+        game.dig(4);
+        assert_eq!(game.cycle_flag(0, true), Outcome::Marked(Some(Flag::Tag)));
+        assert_eq!(game.chord(4), Outcome::Won);
+    }
+
+    #[test]
+    fn test_chord_a_mine() {
+        let mut game = corner_game();
+        game.dig(4);
+        game.cycle_flag(1, true);
+        assert_eq!(game.chord(4), Outcome::Lost(0));
+        assert_eq!(game.status(), GameStatus::Lost);
+    }
+
+    #[test]
+    fn test_flag_cycle() {
+        let mut game = corner_game();
+        assert_eq!(game.cycle_flag(0, true), Outcome::Marked(Some(Flag::Tag)));
+        assert_eq!(
+            game.cycle_flag(0, true),
+            Outcome::Marked(Some(Flag::Question))
+        );
+        assert_eq!(game.cycle_flag(0, true), Outcome::Marked(None));
+        game.dig(4);
+        assert_eq!(game.cycle_flag(4, true), Outcome::Ignored);
+    }
+
+    #[test]
+    fn test_actions_ignored_once_over() {
+        let mut game = corner_game();
+        game.dig(0);
+        assert_eq!(game.dig(8), Outcome::Ignored);
+        assert_eq!(game.chord(4), Outcome::Ignored);
+        assert_eq!(game.cycle_flag(1, true), Outcome::Ignored);
+    }
+
+    #[test]
+    fn test_time_stops_once_over() {
+        let clock = Rc::new(ManualClock::default());
+        let mut game = Game::new(corner_grid(), clock.clone());
+        game.dig(4);
+        clock.advance(2345);
+        assert_eq!(game.elapsed_ms(), 2345);
+        assert_eq!(game.result(), None);
+        game.dig(8);
+        clock.advance(1000);
+        assert_eq!(game.elapsed_ms(), 2345);
+        assert_eq!(
+            game.result(),
+            Some(GameResult {
+                won: true,
+                config: GameConfig::new(3, 3, 1).unwrap(),
+                replay: "3x3x1-i-0".parse().unwrap(),
+                elapsed_ms: 2345,
+                clicks: Clicks {
+                    left: 2,
+                    right: 0,
+                    chord: 0,
+                },
+                hints: 0,
+                three_bv: 1,
+                zini: 1,
+            })
+        );
+        let result = game.result().unwrap();
+        assert_eq!(result.three_bv_per_second(), Some(1.0 / 2.345));
+        assert_eq!(result.efficiency(), Some(0.5));
+        assert_eq!(efficiency(1, 0), None);
+    }
+
+    #[test]
+    fn test_cascade_wins() {
+        let mut game = new_game(Grid::from_layout(
+            3,
+            4,
+            ". . . .
+             . . . .
+             . . . *",
+        ));
+        assert_eq!(game.dig(0), Outcome::Won);
+        assert_eq!(game.status(), GameStatus::Won);
+    }
+
+    #[test]
+    /// A safe cell flagged by mistake stops the cascade, so the game is only won
+    /// once the flag is removed and the cell dug
+    fn test_flagged_cell_in_cascade() {
+        let mut game = new_game(Grid::from_layout(
+            3,
+            4,
+            ". . . .
+             . . . .
+             . . . *",
+        ));
+        game.cycle_flag(5, false);
+        assert_eq!(game.dig(0), Outcome::Revealed(10));
+        assert_eq!(game.grid().safe_cells_left(), 1);
+        assert_eq!(game.dig(5), Outcome::Ignored);
+        assert_eq!(game.cycle_flag(5, false), Outcome::Marked(None));
+        assert_eq!(game.dig(5), Outcome::Won);
+    }
+
+    #[test]
+    fn test_last_cell_wins_once() {
+        let mut game = corner_game();
+        for idx in [1, 3, 4] {
+            assert_eq!(game.dig(idx), Outcome::Revealed(1));
+        }
+        assert_eq!(game.dig(8), Outcome::Won);
+        assert_eq!(game.dig(8), Outcome::Ignored);
+        assert_eq!(game.chord(4), Outcome::Ignored);
+        assert_eq!(game.status(), GameStatus::Won);
+    }
+
+    #[test]
+    /// Digging every safe cell of random grids in any order wins exactly once
+    fn test_win_is_never_missed() {
+        use crate::config::GameConfig;
+        use crate::state::GenerationPolicy;
+        use rand::seq::SliceRandom;
+        use rand::SeedableRng;
+        use rand_chacha::ChaCha8Rng;
+
+        for seed in 0..50 {
+            let mut game = new_game(Grid::from_seed(
+                GameConfig::BEGINNER,
+                GenerationPolicy::SafeFirstClick,
+                seed,
+            ));
+            let mut order: Vec<usize> = (0..81).collect();
+            order.shuffle(&mut ChaCha8Rng::seed_from_u64(seed));
+            assert!(matches!(game.dig(order[0]), Outcome::Revealed(_)));
+            let mut wins = 0;
+            for idx in order {
+                if game.grid().grid_vec[idx].data == CellData::Mine {
+                    continue;
+                }
+                match game.dig(idx) {
+                    Outcome::Won => wins += 1,
+                    Outcome::Revealed(_) | Outcome::Ignored => {}
+                    outcome => panic!("unexpected {:?}", outcome),
+                }
+            }
+            assert_eq!(wins, 1);
+            assert_eq!(game.status(), GameStatus::Won);
+            assert_eq!(game.grid().safe_cells_left(), 0);
+        }
+    }
+
+    #[test]
+    fn test_cell_views_while_playing() {
+        let mut game = corner_game();
+        game.dig(4);
+        game.cycle_flag(0, true);
+        game.cycle_flag(1, true);
+        game.cycle_flag(1, true);
+        assert_eq!(game.cell_view(0), CellView::Marked(Flag::Tag));
+        assert_eq!(game.cell_view(1), CellView::Marked(Flag::Question));
+        assert_eq!(game.cell_view(2), CellView::Hidden);
+        assert_eq!(game.cell_view(4), CellView::Revealed(1));
+    }
+
+    #[test]
+    /// ```text
+    /// * * 1
+    /// 2 2 1
+    /// 0 0 0
+    /// ```
+    /// Chording the center with a wrong flag on the top right cell explodes
+    /// the top middle mine.
+    fn test_cell_views_once_lost() {
+        let mut game = new_game(Grid::from_layout(
+            3,
+            3,
+            "* * .
+             . . .
+             . . .",
+        ));
+        game.dig(8);
+        game.cycle_flag(0, true);
+        game.cycle_flag(2, true);
+        assert_eq!(game.chord(4), Outcome::Lost(1));
+        assert_eq!(game.cell_view(0), CellView::CorrectFlag);
+        assert_eq!(game.cell_view(1), CellView::ExplodedMine);
+        assert_eq!(game.cell_view(2), CellView::WrongFlag);
+        assert_eq!(game.cell_view(4), CellView::Revealed(2));
+        assert_eq!(game.cell_view(8), CellView::Revealed(0));
+    }
+
+    #[test]
+    fn test_wrong_flags_and_missed_mines_once_lost() {
+        let mut game = new_game(Grid::from_layout(
+            2,
+            3,
+            "* . *
+             . . .",
+        ));
+        game.cycle_flag(1, true);
+        game.cycle_flag(5, true);
+        game.cycle_flag(5, true);
+        assert_eq!(game.dig(0), Outcome::Lost(0));
+        assert_eq!(game.cell_view(0), CellView::ExplodedMine);
+        assert_eq!(game.cell_view(1), CellView::WrongFlag);
+        assert_eq!(game.cell_view(2), CellView::Mine);
+        assert_eq!(game.cell_view(3), CellView::Hidden);
+        assert_eq!(game.cell_view(5), CellView::Marked(Flag::Question));
+    }
+
+    #[test]
+    fn test_winning_flags_every_mine() {
+        let mut game = new_game(Grid::from_layout(
+            2,
+            3,
+            "* . *
+             . . .",
+        ));
+        game.cycle_flag(0, true);
+        game.cycle_flag(2, true);
+        game.cycle_flag(2, true);
+        assert_eq!(game.dig(4), Outcome::Revealed(1));
+        game.dig(1);
+        game.dig(3);
+        assert_eq!(game.dig(5), Outcome::Won);
+        assert_eq!(game.cell_view(0), CellView::CorrectFlag);
+        assert_eq!(game.cell_view(2), CellView::CorrectFlag);
+        assert_eq!(game.cell_view(4), CellView::Revealed(2));
+    }
+
+    #[test]
+    fn test_first_dig_starts_the_game() {
+        let clock = Rc::new(ManualClock::default());
+        let mut game = Game::new(corner_grid(), clock.clone());
+        assert_eq!(game.status(), GameStatus::NotStarted);
+        clock.advance(5000);
+        assert_eq!(game.elapsed_ms(), 0);
+        // marking a cell does not start the game, nor does an ignored dig
+        game.cycle_flag(0, true);
+        assert_eq!(game.dig(0), Outcome::Ignored);
+        assert_eq!(game.status(), GameStatus::NotStarted);
+        assert_eq!(game.dig(4), Outcome::Revealed(1));
+        assert_eq!(game.status(), GameStatus::Playing);
+        clock.advance(1);
+        assert_eq!(game.elapsed_ms(), 1);
+    }
+
+    #[test]
+    fn test_pause_and_resume() {
+        let clock = Rc::new(ManualClock::default());
+        let mut game = Game::new(corner_grid(), clock.clone());
+        game.dig(4);
+        clock.advance(100);
+        assert_eq!(game.pause(), Ok(()));
+        assert_eq!(game.status(), GameStatus::Paused);
+        clock.advance(60_000);
+        assert_eq!(game.elapsed_ms(), 100);
+        assert_eq!(game.dig(8), Outcome::Ignored);
+        assert_eq!(game.chord(4), Outcome::Ignored);
+        assert_eq!(game.cycle_flag(0, true), Outcome::Ignored);
+        assert_eq!(game.resume(), Ok(()));
+        assert_eq!(game.status(), GameStatus::Playing);
+        clock.advance(50);
+        assert_eq!(game.dig(8), Outcome::Won);
+        assert_eq!(game.result().map(|result| result.elapsed_ms), Some(150));
+    }
+
+    #[test]
+    fn test_invalid_transitions() {
+        let mut game = corner_game();
+        assert_eq!(
+            game.pause(),
+            Err(TransitionError {
+                from: GameStatus::NotStarted,
+                to: GameStatus::Paused
+            })
+        );
+        assert!(game.resume().is_err());
+        game.dig(4);
+        assert!(game.resume().is_err());
+        game.pause().unwrap();
+        assert!(game.pause().is_err());
+        game.resume().unwrap();
+        game.dig(0);
+        assert_eq!(game.status(), GameStatus::Lost);
+        assert!(game.pause().is_err());
+        assert!(game.resume().is_err());
+    }
+
+    #[test]
+    fn test_lifecycle_table() {
+        use GameStatus::*;
+        let all = [NotStarted, Playing, Paused, Lost, Won];
+        let allowed = [
+            (NotStarted, Playing),
+            (Playing, Paused),
+            (Paused, Playing),
+            (Playing, Won),
+            (Playing, Lost),
+        ];
+        for from in all {
+            for to in all {
+                assert_eq!(from.can_become(to), allowed.contains(&(from, to)));
+            }
+        }
+        assert!(Won.is_over() && Lost.is_over());
+        assert!(!NotStarted.is_over() && !Playing.is_over() && !Paused.is_over());
+    }
+
+    #[test]
+    fn test_clicks() {
+        let mut game = corner_game();
+        game.cycle_flag(0, true);
+        // ignored by the cell, but still a click
+        game.dig(0);
+        game.dig(4);
+        game.chord(1);
+        game.pause().unwrap();
+        game.dig(8);
+        assert_eq!(game.clicks().total(), 4);
+        game.resume().unwrap();
+        game.dig(8);
+        game.dig(8);
+        assert_eq!(game.status(), GameStatus::Won);
+        assert_eq!(
+            game.clicks(),
+            Clicks {
+                left: 3,
+                right: 1,
+                chord: 1,
+            }
+        );
+    }
+
+    #[test]
+    /// The right button of a chord lands on a revealed cell, which marks nothing
+    fn test_right_click_on_revealed_cell() {
+        let mut game = corner_game();
+        game.dig(4);
+        let clicks = game.clicks();
+        assert_eq!(game.cycle_flag(4, true), Outcome::Ignored);
+        assert_eq!(game.clicks(), clicks);
+    }
+
+    #[test]
+    fn test_hints() {
+        let mut game = new_game(Grid::from_layout(
+            3,
+            3,
+            "* . .
+             . . .
+             . . *",
+        ));
+        assert_eq!(game.hint(), None);
+        game.dig(2);
+        let hint = game.hint().unwrap();
+        assert_eq!(hint.idx(), 6);
+        game.pause().unwrap();
+        assert_eq!(game.hint(), None);
+        game.resume().unwrap();
+        game.hint();
+        assert_eq!(game.hints(), 2);
+        game.dig(6);
+        assert_eq!(game.result().unwrap().hints, 2);
+    }
+}
@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::replay::ReplayCode;
+use crate::save::{read_envelope, write_envelope, LoadError};
+
+/// Version written by `HighScores::to_json`
+pub const HIGH_SCORES_VERSION: u32 = 2;
+/// Number of scores kept per board configuration
+pub const TABLE_SIZE: usize = 10;
+
+/// A won game worth remembering
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct HighScore {
+    pub name: String,
+    pub elapsed_ms: u64,
+    /// When the game was won, in milliseconds since the Unix epoch
+    pub date_ms: u64,
+    /// Code of the board, which version 1 did not keep: its seed alone could
+    /// not lay the board out again
+    #[serde(default)]
+    pub replay: Option<ReplayCode>,
+    /// 3BV of the board, see `Grid::three_bv`, to tell easy boards from hard ones
+    pub three_bv: usize,
+}
+
+/// The best scores of one board configuration, fastest first
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+struct Table {
+    n_rows: usize,
+    n_cols: usize,
+    n_mines: usize,
+    scores: Vec<HighScore>,
+}
+
+impl Table {
+    fn is_for(&self, config: GameConfig) -> bool {
+        (self.n_rows, self.n_cols, self.n_mines)
+            == (config.n_rows(), config.n_cols(), config.n_mines())
+    }
+}
+
+/// Tables of the `TABLE_SIZE` fastest wins, one per board configuration,
+/// custom ones included
+#[derive(Eq, PartialEq, Default, Debug)]
+pub struct HighScores {
+    tables: Vec<Table>,
+}
+
+/// The high scores as stored, next to their version
+#[derive(Serialize, Deserialize)]
+struct StoredTables<T> {
+    tables: T,
+}
+
+impl HighScores {
+    /// Returns the scores of `config`, fastest first
+    pub fn table(&self, config: GameConfig) -> &[HighScore] {
+        self.tables
+            .iter()
+            .find(|table| table.is_for(config))
+            .map_or(&[], |table| &table.scores)
+    }
+
+    /// Returns every configuration with at least one score, from the smallest grid
+    pub fn configs(&self) -> Vec<GameConfig> {
+        let mut configs: Vec<GameConfig> = self
+            .tables
+            .iter()
+            .filter_map(|table| GameConfig::new(table.n_rows, table.n_cols, table.n_mines).ok())
+            .collect();
+        configs.sort_by_key(|config| (config.n_cells(), config.n_mines()));
+        configs
+    }
+
+    /// Returns the rank, from 0, that a win in `elapsed_ms` would take in the table
+    /// of `config`, or `None` when it is too slow to enter it
+    pub fn rank(&self, config: GameConfig, elapsed_ms: u64) -> Option<usize> {
+        // a tie goes after the scores already in the table
+        let rank = self
+            .table(config)
+            .iter()
+            .take_while(|score| score.elapsed_ms <= elapsed_ms)
+            .count();
+        (rank < TABLE_SIZE).then_some(rank)
+    }
+
+    /// Inserts `score` in the table of `config` if it is fast enough, and returns its rank
+    pub fn insert(&mut self, config: GameConfig, score: HighScore) -> Option<usize> {
+        let rank = self.rank(config, score.elapsed_ms)?;
+        let table = match self.tables.iter().position(|table| table.is_for(config)) {
+            Some(position) => &mut self.tables[position],
+            None => {
+                self.tables.push(Table {
+                    n_rows: config.n_rows(),
+                    n_cols: config.n_cols(),
+                    n_mines: config.n_mines(),
+                    scores: Vec::new(),
+                });
+                self.tables.last_mut().unwrap()
+            }
+        };
+        table.scores.insert(rank, score);
+        table.scores.truncate(TABLE_SIZE);
+        Some(rank)
+    }
+
+    /// Returns the tables as versioned JSON, to be read back by `from_json`
+    pub fn to_json(&self) -> String {
+        write_envelope(
+            HIGH_SCORES_VERSION,
+            StoredTables {
+                tables: &self.tables,
+            },
+        )
+    }
+
+    /// Reads tables written by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let (_, StoredTables { tables }): (u32, StoredTables<Vec<Table>>) =
+            read_envelope(json, 1..=HIGH_SCORES_VERSION)?;
+        let is_valid = |table: &Table| {
+            GameConfig::new(table.n_rows, table.n_cols, table.n_mines).is_ok()
+                && table.scores.len() <= TABLE_SIZE
+                && table
+                    .scores
+                    .windows(2)
+                    .all(|pair| pair[0].elapsed_ms <= pair[1].elapsed_ms)
+        };
+        if !tables.iter().all(is_valid) {
+            return Err(LoadError::Inconsistent(
+                "a table is not a list of the fastest wins",
+            ));
+        }
+        Ok(HighScores { tables })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn score(name: &str, elapsed_ms: u64) -> HighScore {
+        HighScore {
+            name: String::from(name),
+            elapsed_ms,
+            date_ms: 1_600_000_000_000,
+            replay: "9x9x10-s40-1".parse().ok(),
+            three_bv: 30,
+        }
+    }
+
+    fn names(scores: &[HighScore]) -> Vec<&str> {
+        scores.iter().map(|score| score.name.as_str()).collect()
+    }
+
+    #[test]
+    fn test_scores_are_ranked_by_time() {
+        let mut high_scores = HighScores::default();
+        assert_eq!(high_scores.rank(GameConfig::BEGINNER, 99_999), Some(0));
+        assert_eq!(
+            high_scores.insert(GameConfig::BEGINNER, score("b", 2000)),
+            Some(0)
+        );
+        assert_eq!(
+            high_scores.insert(GameConfig::BEGINNER, score("a", 1000)),
+            Some(0)
+        );
+        assert_eq!(
+            high_scores.insert(GameConfig::BEGINNER, score("c", 2000)),
+            Some(2)
+        );
+        assert_eq!(
+            names(high_scores.table(GameConfig::BEGINNER)),
+            ["a", "b", "c"]
+        );
+        assert!(high_scores.table(GameConfig::EXPERT).is_empty());
+    }
+
+    #[test]
+    fn test_tables_keep_the_fastest() {
+        let mut high_scores = HighScores::default();
+        for elapsed_ms in 1..=TABLE_SIZE as u64 {
+            high_scores.insert(GameConfig::BEGINNER, score("slow", elapsed_ms * 1000));
+        }
+        assert_eq!(high_scores.rank(GameConfig::BEGINNER, 10_000), None);
+        assert_eq!(
+            high_scores.insert(GameConfig::BEGINNER, score("late", 20_000)),
+            None
+        );
+        assert_eq!(
+            high_scores.insert(GameConfig::BEGINNER, score("fast", 500)),
+            Some(0)
+        );
+        let table = high_scores.table(GameConfig::BEGINNER);
+        assert_eq!(table.len(), TABLE_SIZE);
+        assert_eq!(table[0].name, "fast");
+        assert_eq!(table[TABLE_SIZE - 1].elapsed_ms, 9000);
+    }
+
+    #[test]
+    fn test_custom_boards_have_their_own_tables() {
+        let mut high_scores = HighScores::default();
+        let custom = GameConfig::new(9, 9, 11).unwrap();
+        high_scores.insert(custom, score("custom", 5000));
+        high_scores.insert(GameConfig::EXPERT, score("expert", 90_000));
+        high_scores.insert(GameConfig::BEGINNER, score("beginner", 3000));
+        assert_eq!(names(high_scores.table(custom)), ["custom"]);
+        assert_eq!(
+            high_scores.configs(),
+            [GameConfig::BEGINNER, custom, GameConfig::EXPERT]
+        );
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(GameConfig::BEGINNER, score("a", 1000));
+        high_scores.insert(GameConfig::new(5, 5, 5).unwrap(), score("b", 2000));
+        assert_eq!(
+            HighScores::from_json(&high_scores.to_json()),
+            Ok(high_scores)
+        );
+        assert_eq!(
+            HighScores::from_json("{\"version\":7,\"tables\":[]}"),
+            Err(LoadError::UnsupportedVersion(7))
+        );
+        let version_1 = "{\"version\":1,\"tables\":[{\"n_rows\":9,\"n_cols\":9,\
+            \"n_mines\":10,\"scores\":[{\"name\":\"a\",\"elapsed_ms\":1000,\
+            \"date_ms\":0,\"seed\":1,\"three_bv\":30}]}]}";
+        let high_scores = HighScores::from_json(version_1).unwrap();
+        assert_eq!(high_scores.table(GameConfig::BEGINNER)[0].replay, None);
+    }
+
+    #[test]
+    fn test_unordered_table_is_rejected() {
+        let mut high_scores = HighScores::default();
+        high_scores.insert(GameConfig::BEGINNER, score("a", 1000));
+        high_scores.insert(GameConfig::BEGINNER, score("b", 2000));
+        let json = high_scores
+            .to_json()
+            .replace("\"elapsed_ms\":1000", "\"elapsed_ms\":3000");
+        assert!(matches!(
+            HighScores::from_json(&json),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+}
@@ -0,0 +1,190 @@
+use crate::accessibility::position;
+use crate::probability::mine_probabilities;
+use crate::solver::{deduce, Board, Constraint, Deduction, Origin, Rule, Verdict};
+
+/// A cell worth digging next, found from what the player sees
+#[derive(Clone, Debug, PartialEq)]
+pub enum Hint {
+    /// The solver proves the cell safe
+    Safe(Deduction),
+    /// No cell is certainly safe, and this one has the lowest chance of a mine
+    Guess { idx: usize, chance: f64 },
+}
+
+/// Returns "no mine", "1 mine" or "N mines"
+fn mines_text(mines: usize) -> String {
+    match mines {
+        0 => String::from("no mine"),
+        1 => String::from("1 mine"),
+        mines => format!("{} mines", mines),
+    }
+}
+
+/// Returns `text` starting with a capital letter
+fn capitalize(text: &str) -> String {
+    let mut chars = text.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// Returns what a constraint tells, like "the 2 at row 1 column 3 has 1 mine
+/// left among 3 cells in doubt"
+fn describe(constraint: &Constraint, board: &Board) -> String {
+    let cells = match constraint.cells.len() {
+        1 => String::from("1 cell in doubt"),
+        count => format!("{} cells in doubt", count),
+    };
+    match constraint.origin {
+        Origin::Number(idx) => format!(
+            "the {} at {} has {} left among {}",
+            board.numbers[idx].unwrap_or_default(),
+            position(idx, board.n_rows, board.n_cols),
+            mines_text(constraint.mines),
+            cells
+        ),
+        Origin::MineCount => format!(
+            "the grid has {} left among {}",
+            mines_text(constraint.mines),
+            cells
+        ),
+    }
+}
+
+impl Hint {
+    /// Returns the index of the cell to dig
+    pub fn idx(&self) -> usize {
+        match self {
+            Hint::Safe(deduction) => deduction.idx,
+            Hint::Guess { idx, .. } => *idx,
+        }
+    }
+
+    /// Returns why the cell of the hint is the one to dig, `board` being the one
+    /// the hint was found on
+    pub fn explanation(&self, board: &Board) -> String {
+        let cell = position(self.idx(), board.n_rows, board.n_cols);
+        match self {
+            Hint::Safe(deduction) => {
+                let reasons: Vec<String> = deduction
+                    .reasons
+                    .iter()
+                    .map(|constraint| describe(constraint, board))
+                    .collect();
+                let conclusion = match deduction.rule {
+                    Rule::Single => "so none of its cells holds a mine",
+                    Rule::Subset | Rule::Overlap => {
+                        "so the cells they share hold all the mines of one of them, \
+                         whose other cells are safe"
+                    }
+                };
+                format!(
+                    "{} is safe: {}, {}.",
+                    capitalize(&cell),
+                    reasons.join(", and "),
+                    conclusion
+                )
+            }
+            // only a layout of several numbers tells, which the solver does not look at
+            Hint::Guess { chance, .. } if *chance == 0.0 => format!(
+                "{} is safe: no layout of mines fitting the numbers puts one there.",
+                capitalize(&cell)
+            ),
+            Hint::Guess { chance, .. } => format!(
+                "No cell is certainly safe. {} has the lowest chance of a mine: {:.0}%.",
+                capitalize(&cell),
+                chance * 100.0
+            ),
+        }
+    }
+}
+
+/// Returns a cell the solver proves safe, or else the cell least likely to hold
+/// a mine, or `None` when no layout of mines fits the board
+///
+/// The chances of a mine are only worked out when no cell is proven safe, and
+/// only within the budget of `mine_probabilities`: a frontier too large for it
+/// gets no hint rather than a slow one.
+pub fn hint(board: &Board) -> Option<Hint> {
+    if let Some(deduction) = deduce(board)
+        .into_iter()
+        .find(|deduction| deduction.verdict == Verdict::Safe)
+    {
+        return Some(Hint::Safe(deduction));
+    }
+    // the first of the cells sharing the lowest chance
+    mine_probabilities(board)?
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, chance)| chance.map(|chance| (idx, chance)))
+        .min_by(|(_, first), (_, second)| first.total_cmp(second))
+        .map(|(idx, chance)| Hint::Guess { idx, chance })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safe_cell() {
+        let board = Board::from_layout(
+            2,
+            3,
+            2,
+            "# # #
+             1 1 #",
+        );
+        let hint = hint(&board).unwrap();
+        assert_eq!(hint.idx(), 2);
+        assert!(matches!(&hint, Hint::Safe(deduction) if deduction.rule == Rule::Subset));
+        assert_eq!(
+            hint.explanation(&board),
+            "Row 1 column 3 is safe: the 1 at row 2 column 1 has 1 mine left among \
+             2 cells in doubt, and the 1 at row 2 column 2 has 1 mine left among \
+             4 cells in doubt, so the cells they share hold all the mines of one \
+             of them, whose other cells are safe."
+        );
+    }
+
+    #[test]
+    fn test_single_rule_explanation() {
+        let board = Board::from_layout(1, 3, 1, "# 0 #");
+        assert_eq!(
+            hint(&board).unwrap().explanation(&board),
+            "Row 1 column 1 is safe: the 0 at row 1 column 2 has no mine left among \
+             2 cells in doubt, so none of its cells holds a mine."
+        );
+    }
+
+    #[test]
+    /// Nothing is certain, but the neighbors of the 1 are less likely to be mined
+    /// than the right column, which holds the other mine
+    fn test_lowest_chance() {
+        let board = Board::from_layout(
+            3,
+            4,
+            2,
+            "# # # #
+             # 1 # #
+             # # # #",
+        );
+        let hint = hint(&board).unwrap();
+        assert_eq!(
+            hint,
+            Hint::Guess {
+                idx: 0,
+                chance: 0.125
+            }
+        );
+        assert_eq!(
+            hint.explanation(&board),
+            "No cell is certainly safe. Row 1 column 1 has the lowest chance of a mine: 12%."
+        );
+    }
+
+    #[test]
+    fn test_no_hint_on_a_contradiction() {
+        assert_eq!(hint(&Board::from_layout(1, 3, 1, "# 2 #")), None);
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+
+use serde::{Deserialize, Serialize};
+
+use crate::save::{read_envelope, write_envelope, LoadError};
+
+/// Version written by `KeyBindings::to_json`
+pub const KEY_BINDINGS_VERSION: u32 = 1;
+
+/// Where the cursor goes when it moves by one cell
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl Direction {
+    /// Returns the position next to `xy` in this direction, or `xy` itself
+    /// when it is on the edge of the grid
+    pub fn step(&self, xy: (usize, usize), n_rows: usize, n_cols: usize) -> (usize, usize) {
+        let (row, col) = xy;
+        match self {
+            Direction::Up => (row.saturating_sub(1), col),
+            Direction::Down => ((row + 1).min(n_rows.saturating_sub(1)), col),
+            Direction::Left => (row, col.saturating_sub(1)),
+            Direction::Right => (row, (col + 1).min(n_cols.saturating_sub(1))),
+        }
+    }
+}
+
+/// What a key does when the board has the focus
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub enum KeyAction {
+    Move(Direction),
+    Dig,
+    Flag,
+    Chord,
+    Reset,
+    Pause,
+}
+
+impl KeyAction {
+    pub const ALL: [KeyAction; 9] = [
+        KeyAction::Move(Direction::Up),
+        KeyAction::Move(Direction::Down),
+        KeyAction::Move(Direction::Left),
+        KeyAction::Move(Direction::Right),
+        KeyAction::Dig,
+        KeyAction::Flag,
+        KeyAction::Chord,
+        KeyAction::Reset,
+        KeyAction::Pause,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyAction::Move(Direction::Up) => "Move up",
+            KeyAction::Move(Direction::Down) => "Move down",
+            KeyAction::Move(Direction::Left) => "Move left",
+            KeyAction::Move(Direction::Right) => "Move right",
+            KeyAction::Dig => "Dig",
+            KeyAction::Flag => "Flag",
+            KeyAction::Chord => "Chord",
+            KeyAction::Reset => "Reset",
+            KeyAction::Pause => "Pause",
+        }
+    }
+}
+
+/// The vim-style movement keys, which can be turned on next to the bindings
+pub const VIM_KEYS: [(&str, Direction); 4] = [
+    ("h", Direction::Left),
+    ("j", Direction::Down),
+    ("k", Direction::Up),
+    ("l", Direction::Right),
+];
+
+/// Returns `key` the way it is bound, so that a letter typed with Shift or
+/// Caps Lock still does what it is bound to
+fn normalize(key: &str) -> String {
+    if key.chars().count() == 1 {
+        key.to_lowercase()
+    } else {
+        String::from(key)
+    }
+}
+
+/// Returns `key`, a `KeyboardEvent.key` value, the way it is shown to the player
+pub fn key_label(key: &str) -> String {
+    match key {
+        " " => String::from("Space"),
+        key if key.chars().count() == 1 => key.to_uppercase(),
+        key => String::from(key),
+    }
+}
+
+/// A key, as named by `KeyboardEvent.key`, and what it does
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Debug)]
+pub struct Binding {
+    pub key: String,
+    pub action: KeyAction,
+}
+
+/// The keys playing the game, at most one action per key
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct KeyBindings {
+    bindings: Vec<Binding>,
+    vim_keys: bool,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let bindings = [
+            ("ArrowUp", KeyAction::Move(Direction::Up)),
+            ("ArrowDown", KeyAction::Move(Direction::Down)),
+            ("ArrowLeft", KeyAction::Move(Direction::Left)),
+            ("ArrowRight", KeyAction::Move(Direction::Right)),
+            (" ", KeyAction::Dig),
+            ("Enter", KeyAction::Dig),
+            ("f", KeyAction::Flag),
+            ("d", KeyAction::Chord),
+            ("r", KeyAction::Reset),
+            ("p", KeyAction::Pause),
+        ]
+        .iter()
+        .map(|(key, action)| Binding {
+            key: String::from(*key),
+            action: *action,
+        })
+        .collect();
+        KeyBindings {
+            bindings,
+            vim_keys: false,
+        }
+    }
+}
+
+/// The bindings as stored, next to their version
+#[derive(Serialize, Deserialize)]
+struct StoredBindings<T> {
+    vim_keys: bool,
+    bindings: T,
+}
+
+impl KeyBindings {
+    /// Returns what `key` does, a binding taking precedence over a vim-style key
+    pub fn action(&self, key: &str) -> Option<KeyAction> {
+        let key = normalize(key);
+        let bound = self
+            .bindings
+            .iter()
+            .find(|binding| binding.key == key)
+            .map(|binding| binding.action);
+        let vim = || {
+            VIM_KEYS
+                .iter()
+                .find(|(vim_key, _)| self.vim_keys && *vim_key == key)
+                .map(|(_, direction)| KeyAction::Move(*direction))
+        };
+        bound.or_else(vim)
+    }
+
+    /// Returns the keys bound to `action`, vim-style keys left out
+    pub fn keys(&self, action: KeyAction) -> Vec<&str> {
+        self.bindings
+            .iter()
+            .filter(|binding| binding.action == action)
+            .map(|binding| binding.key.as_str())
+            .collect()
+    }
+
+    pub fn vim_keys(&self) -> bool {
+        self.vim_keys
+    }
+
+    pub fn set_vim_keys(&mut self, vim_keys: bool) {
+        self.vim_keys = vim_keys;
+    }
+
+    /// Makes `key` the only key of `action`, taking it away from any other action
+    pub fn rebind(&mut self, action: KeyAction, key: &str) {
+        let key = normalize(key);
+        self.bindings
+            .retain(|binding| binding.action != action && binding.key != key);
+        self.bindings.push(Binding { key, action });
+    }
+
+    /// Returns the bindings as versioned JSON, to be read back by `from_json`
+    pub fn to_json(&self) -> String {
+        write_envelope(
+            KEY_BINDINGS_VERSION,
+            StoredBindings {
+                vim_keys: self.vim_keys,
+                bindings: &self.bindings,
+            },
+        )
+    }
+
+    /// Reads bindings written by `to_json`
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let (_, StoredBindings { vim_keys, bindings }): (u32, StoredBindings<Vec<Binding>>) =
+            read_envelope(json, KEY_BINDINGS_VERSION..=KEY_BINDINGS_VERSION)?;
+        let keys: HashSet<&str> = bindings
+            .iter()
+            .map(|binding| binding.key.as_str())
+            .collect();
+        if keys.len() != bindings.len() {
+            return Err(LoadError::Inconsistent("a key is bound to several actions"));
+        }
+        Ok(KeyBindings { bindings, vim_keys })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_steps_stay_in_the_grid() {
+        assert_eq!(Direction::Up.step((0, 2), 3, 4), (0, 2));
+        assert_eq!(Direction::Left.step((1, 0), 3, 4), (1, 0));
+        assert_eq!(Direction::Down.step((2, 1), 3, 4), (2, 1));
+        assert_eq!(Direction::Right.step((1, 3), 3, 4), (1, 3));
+        assert_eq!(Direction::Down.step((1, 1), 3, 4), (2, 1));
+        assert_eq!(Direction::Right.step((1, 1), 3, 4), (1, 2));
+    }
+
+    #[test]
+    fn test_default_bindings() {
+        let bindings = KeyBindings::default();
+        assert_eq!(bindings.action(" "), Some(KeyAction::Dig));
+        assert_eq!(bindings.action("Enter"), Some(KeyAction::Dig));
+        assert_eq!(bindings.action("F"), Some(KeyAction::Flag));
+        assert_eq!(
+            bindings.action("ArrowLeft"),
+            Some(KeyAction::Move(Direction::Left))
+        );
+        assert_eq!(bindings.action("h"), None);
+        assert_eq!(bindings.keys(KeyAction::Dig), [" ", "Enter"]);
+        assert_eq!(key_label(" "), "Space");
+        assert_eq!(key_label("f"), "F");
+    }
+
+    #[test]
+    fn test_vim_keys() {
+        let mut bindings = KeyBindings::default();
+        bindings.set_vim_keys(true);
+        assert_eq!(bindings.action("j"), Some(KeyAction::Move(Direction::Down)));
+        // a binding wins over a vim-style key
+        bindings.rebind(KeyAction::Flag, "l");
+        assert_eq!(bindings.action("l"), Some(KeyAction::Flag));
+        assert_eq!(
+            bindings.keys(KeyAction::Move(Direction::Right)),
+            ["ArrowRight"]
+        );
+    }
+
+    #[test]
+    fn test_rebind() {
+        let mut bindings = KeyBindings::default();
+        bindings.rebind(KeyAction::Dig, "D");
+        assert_eq!(bindings.keys(KeyAction::Dig), ["d"]);
+        assert_eq!(bindings.action(" "), None);
+        // the key was taken away from chording
+        assert!(bindings.keys(KeyAction::Chord).is_empty());
+        assert_eq!(bindings.action("d"), Some(KeyAction::Dig));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut bindings = KeyBindings::default();
+        bindings.rebind(KeyAction::Pause, "Escape");
+        bindings.set_vim_keys(true);
+        assert_eq!(KeyBindings::from_json(&bindings.to_json()), Ok(bindings));
+        let twice = "{\"version\":1,\"vim_keys\":false,\"bindings\":[\
+            {\"key\":\"f\",\"action\":\"Flag\"},\
+            {\"key\":\"f\",\"action\":\"Dig\"}]}";
+        assert!(matches!(
+            KeyBindings::from_json(twice),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+}
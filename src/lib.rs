@@ -0,0 +1,18 @@
+//! The rules of minesweeper, free of any browser or Yew dependency, so that they
+//! can be shared by the web front-end, bots, command line tools or servers.
+
+pub mod accessibility;
+pub mod clock;
+pub mod config;
+pub mod daily;
+pub mod game;
+pub mod highscores;
+pub mod hint;
+pub mod keys;
+pub mod probability;
+pub mod replay;
+pub mod save;
+pub mod share;
+pub mod solver;
+pub mod state;
+pub mod stats;
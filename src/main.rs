@@ -1,44 +1,250 @@
-mod state;
-
-use crate::state::{CellData, Flag, Grid};
-use gloo_timers::callback::Interval;
-use yew::services::ConsoleService;
-use yew::{events::MouseEvent, html, Component, ComponentLink, Html, ShouldRender};
+use gloo_events::EventListener;
+use gloo_timers::callback::{Interval, Timeout};
+use minesweeper::accessibility::{announcement, cell_label};
+use minesweeper::clock::{Clock, Seconds};
+use minesweeper::config::{GameConfig, Preset};
+use minesweeper::daily::{CalendarDate, DailyChallenge, DailyLog};
+use minesweeper::game::{three_bv_per_second, CellView, Game, GameStatus, Outcome};
+use minesweeper::highscores::{HighScore, HighScores};
+use minesweeper::keys::{key_label, KeyAction, KeyBindings};
+use minesweeper::probability::mine_probabilities;
+use minesweeper::replay::ReplayCode;
+use minesweeper::save::{self, LoadError, SavedDaily, SavedGame};
+use minesweeper::share::share_text;
+use minesweeper::solver::Board;
+use minesweeper::state::{Flag, GenerationPolicy, Grid};
+use minesweeper::stats::Statistics;
+use std::rc::Rc;
+use yew::format::Text;
+use yew::services::reader::{File, FileData, ReaderService, ReaderTask};
+use yew::services::storage::{Area, StorageService};
+use yew::services::{ConsoleService, DialogService};
+use yew::utils::{document, window};
+use yew::{
+    events::{FocusEvent, KeyboardEvent, MouseEvent, TouchEvent},
+    html, ChangeData, Component, ComponentLink, Html, InputData, ShouldRender,
+};
 
 // ToDo: Change background colors based on game result
 
-const NUMBER_OF_ROWS: usize = 10;
-const NUMBER_OF_COLUMNS: usize = 10;
-const MINE_PROPORTION: [usize; 3] = [10, 5, 3];
-const DEFAULT_DIFFICULTY: usize = 0;
+const DEFAULT_PRESET: Preset = Preset::Beginner;
+/// How long a touch must be held on a cell to flag it, in milliseconds
+const LONG_PRESS_DELAY: u32 = 500;
+/// How often the timer display is refreshed while playing, in milliseconds
+const TIMER_REFRESH_DELAY: u32 = 100;
+/// Local storage key of the game in progress
+const SAVED_GAME_KEY: &str = "minesweeper.saved_game";
+/// Local storage key of the statistics
+const STATS_KEY: &str = "minesweeper.statistics";
+/// Local storage key of the high score tables
+const HIGH_SCORES_KEY: &str = "minesweeper.high_scores";
+/// Local storage key of the name last entered for a high score
+const PLAYER_NAME_KEY: &str = "minesweeper.player_name";
+/// Local storage key of the scored attempts at daily challenges
+const DAILY_LOG_KEY: &str = "minesweeper.daily_log";
+/// Local storage key of the keys playing the game
+const KEY_BINDINGS_KEY: &str = "minesweeper.key_bindings";
+
+/// The pages of the app, told apart by the hash of the URL
+#[derive(Eq, PartialEq, Clone, Copy)]
+pub enum Route {
+    Game,
+    HighScores,
+}
+
+impl Route {
+    fn hash(&self) -> &'static str {
+        match self {
+            Route::Game => "#/",
+            Route::HighScores => "#/high-scores",
+        }
+    }
+
+    /// Returns the page of the current URL, the game for any unknown hash
+    fn current() -> Self {
+        match window().location().hash() {
+            Ok(hash) if hash == Route::HighScores.hash() => Route::HighScores,
+            _ => Route::Game,
+        }
+    }
+}
+
+/// Returns the date of today in the time zone of the browser
+fn today() -> CalendarDate {
+    let now = js_sys::Date::new_0();
+    CalendarDate {
+        year: now.get_full_year() as i32,
+        month: now.get_month() + 1,
+        day: now.get_date(),
+    }
+}
+
+/// Formats milliseconds since the Unix epoch as a `YYYY-MM-DD` date
+fn format_date(date_ms: u64) -> String {
+    let date = js_sys::Date::new_0();
+    date.set_time(date_ms as f64);
+    String::from(date.to_iso_string())
+        .chars()
+        .take(10)
+        .collect()
+}
+
+/// Reads what is stored under `key` with `from_json`, or returns the default when
+/// nothing is stored or it cannot be read, which is logged
+fn load_or_default<T: Default>(
+    storage: Option<&StorageService>,
+    key: &str,
+    from_json: fn(&str) -> Result<T, LoadError>,
+) -> T {
+    storage
+        .and_then(|storage| storage.restore::<Text>(key).ok())
+        .and_then(|json| match from_json(&json) {
+            Ok(loaded) => Some(loaded),
+            Err(error) => {
+                ConsoleService::log(error.to_string().as_str());
+                None
+            }
+        })
+        .unwrap_or_default()
+}
+
+/// Reads the monotonic `performance.now()` of the browser
+struct PerformanceClock {
+    performance: web_sys::Performance,
+}
+
+impl PerformanceClock {
+    fn new() -> Self {
+        PerformanceClock {
+            performance: web_sys::window()
+                .and_then(|window| window.performance())
+                .expect("the browser provides performance.now()"),
+        }
+    }
+}
+
+impl Clock for PerformanceClock {
+    fn now_ms(&self) -> u64 {
+        self.performance.now() as u64
+    }
+}
+
+/// What a left click on a cell that is not revealed does
+#[derive(Eq, PartialEq, Clone, Copy)]
+pub enum ClickMode {
+    Dig,
+    Flag,
+}
+
+/// The inputs of the custom configuration form
+#[derive(Clone, Copy)]
+pub enum CustomField {
+    Rows,
+    Columns,
+    Mines,
+}
 
 pub enum Msg {
     Clicked((usize, MouseEvent)),
-    Flagged((usize, Flag)),
-    ChangeFlag,
+    Chord(usize),
+    BothButtonsChord(usize),
+    MouseDown,
+    CycleFlag(usize),
+    TouchStart(usize),
+    TouchEnd,
+    LongPress(usize),
+    ChangeClickMode,
+    ToggleQuestionMarks,
     Reset,
     Loss,
     Win,
-    IncrementTimer,
-    ChangeDifficulty,
-}
-
-#[derive(Eq, PartialEq)]
-pub enum GameStatus {
-    Playing,
-    Lost,
-    Won,
+    RefreshTimer,
+    TogglePause,
+    VisibilityChange,
+    SelectPreset(Option<Preset>),
+    CustomInput((CustomField, String)),
+    ApplyCustom,
+    ToggleNoGuess,
+    TogglePractice,
+    ToggleOdds,
+    ReplayInput(String),
+    Replay,
+    ResumeSaved,
+    DiscardSaved,
+    ToggleStats,
+    StartDaily,
+    Share,
+    Hint,
+    KeyDown(KeyboardEvent),
+    BoardFocus(bool),
+    ToggleKeys,
+    RebindKey(KeyAction),
+    CaptureKey(KeyboardEvent),
+    ToggleVimKeys,
+    ResetKeys,
+    RouteChanged,
+    ImportStats(File),
+    StatsFileRead(FileData),
 }
 
 pub struct Model {
     link: ComponentLink<Self>,
-    state: Grid,
-    play_status: GameStatus,
-    selected_flag: Flag,
-    elapsed_time: usize,
+    clock: Rc<dyn Clock>,
+    game: Game,
+    click_mode: ClickMode,
+    question_marks: bool,
     timer_handle: Option<Interval>,
-    empty_cells_left: usize,
-    selected_difficulty_idx: usize,
+    /// Pauses the game when the tab is hidden, for as long as the model lives
+    _visibility_listener: EventListener,
+    /// Follows the hash of the URL to switch pages
+    _route_listener: EventListener,
+    route: Route,
+    custom_selected: bool,
+    custom_rows: String,
+    custom_cols: String,
+    custom_mines: String,
+    config_error: Option<String>,
+    generation_policy: GenerationPolicy,
+    /// Practice games are not recorded, and may show the chances of a mine
+    practice: bool,
+    show_odds: bool,
+    /// The chance of a mine of every cell, while the overlay is shown
+    odds: Option<Vec<Option<f64>>>,
+    replay_input: String,
+    long_press_handle: Option<(usize, Timeout)>,
+    long_pressed_idx: Option<usize>,
+    /// The cell chorded by pressing both buttons, whose click and context menu
+    /// are ignored until the next press
+    chorded_idx: Option<usize>,
+    /// Local storage, when the browser allows it
+    storage: Option<StorageService>,
+    /// A game left unfinished by a previous visit, offered to be resumed
+    saved_game: Option<SavedGame>,
+    stats: Statistics,
+    show_stats: bool,
+    stats_error: Option<String>,
+    /// Reads an imported statistics file, and cancels the reading when dropped
+    stats_reader: Option<ReaderTask>,
+    high_scores: HighScores,
+    /// The daily challenge being played, if the current game is one
+    daily: Option<DailyChallenge>,
+    /// Whether the current daily challenge is the scored attempt of its day
+    daily_scored: bool,
+    daily_log: DailyLog,
+    /// The summary of the finished game last copied to the clipboard
+    shared_text: Option<String>,
+    /// Row and column of the cell played by the keyboard
+    cursor: (usize, usize),
+    /// Whether the board has the focus, which shows the cursor
+    board_focused: bool,
+    key_bindings: KeyBindings,
+    show_keys: bool,
+    /// The action waiting for the next key pressed to be bound to it
+    rebinding: Option<KeyAction>,
+    /// The last news read out by screen readers, from a live region
+    announcement: String,
+    /// The cell last given as a hint, and why it is the one to dig
+    hint: Option<(usize, String)>,
 }
 
 impl Component for Model {
@@ -46,133 +252,508 @@ impl Component for Model {
     type Properties = ();
 
     fn create(_props: Self::Properties, link: ComponentLink<Self>) -> Self {
-        let state = Grid::new(NUMBER_OF_ROWS, NUMBER_OF_COLUMNS, DEFAULT_DIFFICULTY);
-        let timer_link = link.clone();
-        let empty_cells_left = state.grid_vec.len() - state.mine_count();
-        ConsoleService::log(state.to_string().as_str());
+        let config = DEFAULT_PRESET.config();
+        let clock: Rc<dyn Clock> = Rc::new(PerformanceClock::new());
+        let game = Game::new(
+            Grid::new(config, GenerationPolicy::SafeFirstClick),
+            clock.clone(),
+        );
+        let visibility_link = link.clone();
+        let visibility_listener = EventListener::new(&document(), "visibilitychange", move |_| {
+            visibility_link.send_message(Msg::VisibilityChange)
+        });
+        let route_link = link.clone();
+        let route_listener = EventListener::new(&window(), "hashchange", move |_| {
+            route_link.send_message(Msg::RouteChanged)
+        });
+        ConsoleService::log(&format!("{} mines in the grid.", game.grid().mine_count()));
+        let storage = StorageService::new(Area::Local).ok();
+        let saved_game = storage
+            .as_ref()
+            .and_then(|storage| storage.restore::<Text>(SAVED_GAME_KEY).ok())
+            .and_then(|json| match save::load(&json, clock.clone()) {
+                Ok(saved_game) => Some(saved_game),
+                Err(error) => {
+                    ConsoleService::log(error.to_string().as_str());
+                    None
+                }
+            })
+            .filter(|saved_game| saved_game.game.status() == GameStatus::Paused);
+        let stats = load_or_default(storage.as_ref(), STATS_KEY, Statistics::from_json);
+        let daily_log = load_or_default(storage.as_ref(), DAILY_LOG_KEY, DailyLog::from_json);
+        let high_scores = load_or_default(storage.as_ref(), HIGH_SCORES_KEY, HighScores::from_json);
+        let key_bindings =
+            load_or_default(storage.as_ref(), KEY_BINDINGS_KEY, KeyBindings::from_json);
         Model {
             link,
-            state,
-            play_status: GameStatus::Playing,
-            selected_flag: Flag::Dig,
-            elapsed_time: 0,
-            // the Interval tells the model to increment the timer every second
-            timer_handle: Some(Interval::new(1000, move || {
-                timer_link.send_message(Msg::IncrementTimer)
-            })),
-            empty_cells_left,
-            selected_difficulty_idx: DEFAULT_DIFFICULTY,
+            clock,
+            game,
+            click_mode: ClickMode::Dig,
+            question_marks: true,
+            // the timer only runs once the first dig starts the game
+            timer_handle: None,
+            _visibility_listener: visibility_listener,
+            _route_listener: route_listener,
+            route: Route::current(),
+            custom_selected: false,
+            custom_rows: config.n_rows().to_string(),
+            custom_cols: config.n_cols().to_string(),
+            custom_mines: config.n_mines().to_string(),
+            config_error: None,
+            generation_policy: GenerationPolicy::SafeFirstClick,
+            practice: false,
+            show_odds: false,
+            odds: None,
+            replay_input: String::new(),
+            long_press_handle: None,
+            long_pressed_idx: None,
+            chorded_idx: None,
+            storage,
+            saved_game,
+            stats,
+            show_stats: false,
+            stats_error: None,
+            stats_reader: None,
+            high_scores,
+            daily: None,
+            daily_scored: false,
+            daily_log,
+            shared_text: None,
+            cursor: (0, 0),
+            board_focused: false,
+            key_bindings,
+            show_keys: false,
+            rebinding: None,
+            announcement: String::new(),
+            hint: None,
         }
     }
 
     fn update(&mut self, msg: Self::Message) -> ShouldRender {
-        match msg {
-            Msg::Clicked((idx, event)) => {
+        // refreshing the timer display, focusing the board or pressing a button
+        // changes nothing worth saving
+        let changes_game = !matches!(msg, Msg::RefreshTimer | Msg::BoardFocus(_) | Msg::MouseDown);
+        let should_render = match msg {
+            Msg::Clicked((idx, _event)) => {
                 ConsoleService::log(format!("Processing a mouse click on cell #{}", idx).as_str());
-                match self.selected_flag {
-                    Flag::Dig => {
-                        // only reveal a cell if it has not been clicked
-                        // and the game is still progressing
-                        if !self.state.grid_vec[idx].is_clicked
-                            && self.play_status == GameStatus::Playing
-                        {
-                            ConsoleService::log(format!("Digging cell #{}.", idx).as_str());
-                            if let CellData::Mine = self.state.grid_vec[idx].data {
-                                self.link.send_message(Msg::Loss);
-                            } else {
-                                let clicked_cells_count = self.state.reveal_empty_cells(idx);
-                                ConsoleService::log(
-                                    format!(
-                                        "Empty cells left: {}\nNewly revealed: {}",
-                                        self.empty_cells_left, clicked_cells_count
-                                    )
-                                    .as_str(),
-                                );
-                                // ToDo: bugs out
-                                self.empty_cells_left -= clicked_cells_count;
-                                if self.empty_cells_left == 0 {
-                                    self.link.send_message(Msg::Win);
-                                }
-                            }
-                            return true;
-                        }
-                    }
-                    Flag::Tag => {
-                        ConsoleService::log(format!("Tagging cell #{}", idx).as_str());
-                        self.state.grid_vec[idx].flag = Some(self.selected_flag);
-                        return true;
+                // the click following a long press has already been handled
+                if self.long_pressed_idx.take() == Some(idx) {
+                    return false;
+                }
+                // so has the click releasing both buttons of a chord
+                if self.chorded_idx == Some(idx) {
+                    return false;
+                }
+                // the keyboard carries on from the cell last clicked
+                if let Some(xy) =
+                    Grid::idx_to_xy(idx, self.game.grid().n_rows, self.game.grid().n_cols)
+                {
+                    self.cursor = xy;
+                }
+                // clicking a revealed number chords it
+                if self.game.grid().grid_vec[idx].is_clicked {
+                    self.link.send_message(Msg::Chord(idx));
+                    return false;
+                }
+                match self.click_mode {
+                    ClickMode::Dig => self.dig(idx),
+                    ClickMode::Flag => {
+                        self.link.send_message(Msg::CycleFlag(idx));
+                        false
                     }
                 }
-
+            }
+            Msg::Chord(idx) => {
+                ConsoleService::log(format!("Chording cell #{}.", idx).as_str());
+                let outcome = self.game.chord(idx);
+                self.handle_outcome(outcome)
+            }
+            Msg::BothButtonsChord(idx) => {
+                self.chorded_idx = Some(idx);
+                self.link.send_message(Msg::Chord(idx));
+                false
+            }
+            Msg::MouseDown => {
+                self.chorded_idx = None;
                 false
             }
             Msg::Loss => {
-                ConsoleService::log("Game lost.");
-                self.play_status = GameStatus::Lost;
-                self.timer_handle = None;
+                self.log_result();
+                if self.is_recorded() {
+                    self.record_result();
+                }
+                self.finish_daily();
                 true
             }
             Msg::Reset => {
-                self.play_status = GameStatus::Playing;
-                self.state = Grid::new(
-                    NUMBER_OF_ROWS,
-                    NUMBER_OF_COLUMNS,
-                    self.selected_difficulty_idx,
-                );
-                ConsoleService::log(self.state.to_string().as_str());
-                self.elapsed_time = 0;
-                // dump the old timer and create a new one
-                let new_link = self.link.clone();
-                self.timer_handle = Some(Interval::new(1000, move || {
-                    new_link.send_message(Msg::IncrementTimer)
-                }));
-                self.empty_cells_left = self.state.grid_vec.len() - self.state.mine_count();
+                self.restart(self.game.grid().config());
                 true
             }
-            Msg::Flagged((idx, flag)) => {
-                self.state.grid_vec[idx].flag = Some(flag);
+            Msg::CycleFlag(idx) => {
+                // some browsers open the context menu on a long press
+                if let Some((pressed_idx, _)) = self.long_press_handle.take() {
+                    self.long_pressed_idx = Some(pressed_idx);
+                } else if self.long_pressed_idx == Some(idx) || self.chorded_idx == Some(idx) {
+                    return false;
+                }
+                ConsoleService::log(format!("Tagging cell #{}", idx).as_str());
+                let outcome = self.game.cycle_flag(idx, self.question_marks);
+                self.handle_outcome(outcome)
+            }
+            Msg::TouchStart(idx) => {
+                self.long_pressed_idx = None;
+                let press_link = self.link.clone();
+                self.long_press_handle = Some((
+                    idx,
+                    Timeout::new(LONG_PRESS_DELAY, move || {
+                        press_link.send_message(Msg::LongPress(idx))
+                    }),
+                ));
+                false
+            }
+            Msg::TouchEnd => {
+                // dropping the timeout cancels a press that was not long enough
+                self.long_press_handle = None;
+                false
+            }
+            Msg::LongPress(idx) => {
+                self.long_press_handle = None;
+                self.long_pressed_idx = Some(idx);
+                ConsoleService::log(format!("Tagging cell #{} by long press", idx).as_str());
+                let outcome = self.game.cycle_flag(idx, self.question_marks);
+                self.handle_outcome(outcome)
+            }
+            Msg::ChangeClickMode => {
+                ConsoleService::log("Switching the click mode.");
+                self.click_mode = match self.click_mode {
+                    ClickMode::Dig => ClickMode::Flag,
+                    ClickMode::Flag => ClickMode::Dig,
+                };
                 true
             }
-            Msg::ChangeFlag => {
-                ConsoleService::log("Switching the flag.");
-                match self.selected_flag {
-                    Flag::Dig => self.selected_flag = Flag::Tag,
-                    Flag::Tag => self.selected_flag = Flag::Dig,
+            Msg::ToggleQuestionMarks => {
+                self.question_marks = !self.question_marks;
+                true
+            }
+            // the time itself comes from the clock, the interval only redraws it
+            Msg::RefreshTimer => true,
+            Msg::TogglePause => {
+                let transition = if self.game.status() == GameStatus::Paused {
+                    self.game.resume()
+                } else {
+                    self.game.pause()
+                };
+                match transition {
+                    Ok(()) => {
+                        self.announcement = match self.game.status() {
+                            GameStatus::Paused => String::from("Game paused."),
+                            _ => String::from("Game resumed."),
+                        };
+                        true
+                    }
+                    Err(error) => {
+                        ConsoleService::log(error.to_string().as_str());
+                        false
+                    }
+                }
+            }
+            Msg::VisibilityChange => {
+                // only pause automatically, resuming is left to the player
+                document().hidden() && self.game.pause().is_ok()
+            }
+            Msg::Win => {
+                self.log_result();
+                if self.is_recorded() {
+                    self.record_result();
+                    self.record_high_score();
                 }
+                self.finish_daily();
                 true
             }
-            Msg::IncrementTimer => {
-                self.elapsed_time += 1;
+            Msg::Share => {
+                if let Some(text) = share_text(&self.game) {
+                    // the promise is left alone, the text stays on the page if copying fails
+                    let _ = window().navigator().clipboard().write_text(&text);
+                    self.shared_text = Some(text);
+                }
                 true
             }
-            Msg::Win => {
-                ConsoleService::log("Game won.");
-                self.timer_handle = None;
-                self.play_status = GameStatus::Won;
-
+            // the scored attempt at a daily challenge is played without help
+            Msg::Hint if self.daily.is_some() && self.daily_scored => {
+                DialogService::alert("Hints are not given in the scored attempt of the day.");
+                false
+            }
+            Msg::Hint => match self.game.hint() {
+                Some(hint) => {
+                    let explanation = hint.explanation(&Board::from_grid(self.game.grid()));
+                    self.announcement = explanation.clone();
+                    self.hint = Some((hint.idx(), explanation));
+                    true
+                }
+                None => {
+                    if self.game.status() == GameStatus::Playing {
+                        DialogService::alert(
+                            "No cell is certainly safe, and the board is too tangled to weigh the risks.",
+                        );
+                    }
+                    false
+                }
+            },
+            Msg::KeyDown(event) => {
+                let action = match self.key_bindings.action(&event.key()) {
+                    Some(action) if !(event.ctrl_key() || event.alt_key() || event.meta_key()) => {
+                        action
+                    }
+                    _ => return false,
+                };
+                // keeps the arrows and Space from scrolling the page
+                event.prevent_default();
+                let idx = self.cursor_idx();
+                match action {
+                    KeyAction::Move(direction) => {
+                        let grid = self.game.grid();
+                        self.cursor = direction.step(self.cursor, grid.n_rows, grid.n_cols);
+                        true
+                    }
+                    KeyAction::Dig => self.dig(idx),
+                    KeyAction::Flag => {
+                        ConsoleService::log(format!("Tagging cell #{}", idx).as_str());
+                        let outcome = self.game.cycle_flag(idx, self.question_marks);
+                        self.handle_outcome(outcome)
+                    }
+                    KeyAction::Chord => {
+                        self.link.send_message(Msg::Chord(idx));
+                        false
+                    }
+                    KeyAction::Reset => {
+                        self.link.send_message(Msg::Reset);
+                        false
+                    }
+                    KeyAction::Pause => {
+                        self.link.send_message(Msg::TogglePause);
+                        false
+                    }
+                }
+            }
+            Msg::BoardFocus(focused) => {
+                self.board_focused = focused;
                 true
             }
-            Msg::ChangeDifficulty => {
-                self.selected_difficulty_idx =
-                    (self.selected_difficulty_idx + 1).rem_euclid(MINE_PROPORTION.len());
-                self.play_status = GameStatus::Playing;
-                self.state = Grid::new(
-                    NUMBER_OF_ROWS,
-                    NUMBER_OF_COLUMNS,
-                    self.selected_difficulty_idx,
+            Msg::ToggleKeys => {
+                self.show_keys = !self.show_keys;
+                self.rebinding = None;
+                true
+            }
+            Msg::RebindKey(action) => {
+                self.rebinding = match self.rebinding {
+                    Some(rebinding) if rebinding == action => None,
+                    _ => Some(action),
+                };
+                true
+            }
+            Msg::CaptureKey(event) => {
+                let action = match self.rebinding.take() {
+                    Some(action) => action,
+                    None => return false,
+                };
+                let key = event.key();
+                // a modifier alone is not a key to play with, the next key is waited for
+                if matches!(key.as_str(), "Shift" | "Control" | "Alt" | "Meta") {
+                    self.rebinding = Some(action);
+                    return false;
+                }
+                event.prevent_default();
+                // Escape cancels the change
+                if key != "Escape" {
+                    self.key_bindings.rebind(action, &key);
+                    self.store_key_bindings();
+                }
+                true
+            }
+            Msg::ToggleVimKeys => {
+                self.key_bindings
+                    .set_vim_keys(!self.key_bindings.vim_keys());
+                self.store_key_bindings();
+                true
+            }
+            Msg::ResetKeys => {
+                self.key_bindings = KeyBindings::default();
+                self.rebinding = None;
+                self.store_key_bindings();
+                true
+            }
+            Msg::StartDaily => {
+                let challenge = DailyChallenge::new(today());
+                ConsoleService::log(&format!(
+                    "Starting the daily challenge of {}.",
+                    challenge.date
+                ));
+                self.custom_selected = false;
+                self.config_error = None;
+                self.start(challenge.grid());
+                self.daily = Some(challenge);
+                self.daily_scored =
+                    !self.practice && self.daily_log.entry(challenge.day()).is_none();
+                true
+            }
+            Msg::RouteChanged => {
+                self.route = Route::current();
+                // the game is out of sight on other pages
+                if self.route != Route::Game {
+                    let _ = self.game.pause();
+                }
+                true
+            }
+            Msg::SelectPreset(Some(preset)) => {
+                self.custom_selected = false;
+                self.config_error = None;
+                self.restart(preset.config());
+                true
+            }
+            Msg::SelectPreset(None) => {
+                self.custom_selected = true;
+                true
+            }
+            Msg::CustomInput((field, value)) => {
+                match field {
+                    CustomField::Rows => self.custom_rows = value,
+                    CustomField::Columns => self.custom_cols = value,
+                    CustomField::Mines => self.custom_mines = value,
+                }
+                false
+            }
+            Msg::ApplyCustom => {
+                let parsed = (
+                    self.custom_rows.trim().parse::<usize>(),
+                    self.custom_cols.trim().parse::<usize>(),
+                    self.custom_mines.trim().parse::<usize>(),
                 );
-                ConsoleService::log(self.state.to_string().as_str());
-                self.elapsed_time = 0;
-                // dump the old timer and create a new one
-                let new_link = self.link.clone();
-                self.timer_handle = Some(Interval::new(1000, move || {
-                    new_link.send_message(Msg::IncrementTimer)
-                }));
-                self.empty_cells_left = self.state.grid_vec.len() - self.state.mine_count();
-
+                match parsed {
+                    (Ok(n_rows), Ok(n_cols), Ok(n_mines)) => {
+                        match GameConfig::new(n_rows, n_cols, n_mines) {
+                            Ok(config) => {
+                                self.config_error = None;
+                                self.restart(config);
+                            }
+                            Err(error) => self.config_error = Some(error.to_string()),
+                        }
+                    }
+                    _ => {
+                        self.config_error = Some(String::from(
+                            "Rows, columns and mines must be whole numbers.",
+                        ))
+                    }
+                }
+                true
+            }
+            Msg::ToggleNoGuess => {
+                self.generation_policy = match self.generation_policy {
+                    GenerationPolicy::NoGuess => GenerationPolicy::SafeFirstClick,
+                    _ => GenerationPolicy::NoGuess,
+                };
+                self.restart(self.game.grid().config());
+                true
+            }
+            Msg::TogglePractice => {
+                self.practice = !self.practice;
+                self.show_odds = false;
+                self.restart(self.game.grid().config());
+                true
+            }
+            Msg::ToggleOdds => {
+                self.show_odds = !self.show_odds;
+                true
+            }
+            Msg::ReplayInput(value) => {
+                self.replay_input = value;
+                false
+            }
+            Msg::Replay => {
+                // a seed alone does not tell the policy and first dig the layout depends on
+                let input = self.replay_input.trim();
+                match input.parse::<ReplayCode>() {
+                    Ok(code) => {
+                        ConsoleService::log(format!("Replaying {}.", code).as_str());
+                        self.generation_policy = code.policy;
+                        self.start(code.grid());
+                        true
+                    }
+                    Err(error) => {
+                        DialogService::alert(&format!("Invalid replay code {}. {}", input, error));
+                        false
+                    }
+                }
+            }
+            Msg::ResumeSaved => {
+                if let Some(saved_game) = self.saved_game.take() {
+                    self.game = saved_game.game;
+                    self.practice = saved_game.practice;
+                    self.show_odds = false;
+                    // the attempt at a daily challenge is finished in the log once it ends
+                    self.daily = saved_game.daily.map(|daily| daily.challenge);
+                    self.daily_scored = saved_game.daily.is_some_and(|daily| daily.scored);
+                    self.cursor = (0, 0);
+                    self.generation_policy = self.game.grid().policy();
+                    self.custom_selected = false;
+                    if let Err(error) = self.game.resume() {
+                        ConsoleService::log(error.to_string().as_str());
+                    }
+                }
+                true
+            }
+            Msg::ToggleStats => {
+                self.show_stats = !self.show_stats;
+                self.stats_error = None;
                 true
             }
+            Msg::ImportStats(file) => {
+                match ReaderService::read_file(file, self.link.callback(Msg::StatsFileRead)) {
+                    Ok(task) => self.stats_reader = Some(task),
+                    Err(error) => self.stats_error = Some(error.to_string()),
+                }
+                true
+            }
+            Msg::StatsFileRead(file) => {
+                self.stats_reader = None;
+                let imported = String::from_utf8(file.content)
+                    .map_err(|_| String::from("The file is not text."))
+                    .and_then(|json| {
+                        Statistics::from_json(&json).map_err(|error| error.to_string())
+                    });
+                match imported {
+                    Ok(stats) => {
+                        let message = format!(
+                            "Replace your {} recorded games with the {} of {}?",
+                            self.stats.records().len(),
+                            stats.records().len(),
+                            file.name
+                        );
+                        if DialogService::confirm(&message) {
+                            self.stats = stats;
+                            self.store_stats();
+                        }
+                        self.stats_error = None;
+                    }
+                    Err(error) => self.stats_error = Some(error),
+                }
+                true
+            }
+            Msg::DiscardSaved => {
+                self.saved_game = None;
+                if let Some(storage) = self.storage.as_mut() {
+                    storage.remove(SAVED_GAME_KEY);
+                }
+                true
+            }
+        };
+        if changes_game {
+            self.persist_game();
+            self.start_daily_attempt();
+            self.refresh_odds();
+            self.clear_played_hint();
         }
+        self.sync_timer();
+        should_render
     }
 
     fn change(&mut self, _props: Self::Properties) -> ShouldRender {
@@ -185,16 +766,43 @@ impl Component for Model {
                 <h1>
                     { "A minesweeper game made with Rust and Yew." }
                 </h1>
+                <nav id="routes">
+                    <a href={ Route::Game.hash() }>{ "Game" }</a>
+                    <a href={ Route::HighScores.hash() }>{ "High scores" }</a>
+                </nav>
+                {
+                    match self.route {
+                        Route::Game => self.view_game(),
+                        Route::HighScores => self.view_high_scores(),
+                    }
+                }
+            </div>
+        }
+    }
+}
+
+impl Model {
+    /// Returns Html for the game page: its controls and the grid
+    fn view_game(&self) -> Html {
+        html! {
+            <>
                 <div id="controls">
                     <div id="game-status">
                         {
-                            match self.play_status {
+                            match self.game.status() {
                                 GameStatus::Lost => String::from("🤯"),
                                 GameStatus::Won => String::from("😎"),
+                                GameStatus::NotStarted => String::from("🙂"),
                                 GameStatus::Playing => String::from("🤔"),
+                                GameStatus::Paused => String::from("😴"),
                             }
                         }
                     </div>
+                    <div id="mines-left"
+                        class={ if self.game.grid().mines_remaining() < 0 { "over-flagged" } else { "" } }
+                        title="Mines left to find">
+                        { format!("💣 {}", self.game.grid().mines_remaining()) }
+                    </div>
                     <div id="reset" onclick={ self.link.callback(|_| Msg::Reset ) }>
                         <button>
                         { "Reset" }
@@ -202,58 +810,873 @@ impl Component for Model {
                     </div>
                     <div id="current-difficulty">
                         {
-                            format!("Mines/Cells: 1/{}",
-                                MINE_PROPORTION[self.selected_difficulty_idx])
+                            match Preset::of(self.game.grid().config()) {
+                                Some(preset) => format!("{}: {}", preset.name(), self.game.grid().config()),
+                                None => format!("Custom: {}", self.game.grid().config()),
+                            }
+                        }
+                    </div>
+                    <div id="seed">
+                        {
+                            match ReplayCode::of(self.game.grid()) {
+                                Some(code) => format!("Replay code: {}", code),
+                                // the layout waits for the first dig
+                                None => String::from("Replay code: after the first dig"),
+                            }
                         }
                     </div>
-                    <div id="change-difficulty" onclick={ self.link.callback(|_| Msg::ChangeDifficulty ) }>
+                    <div id="change-difficulty">
+                        <select onchange={ self.link.callback(|change| match change {
+                            ChangeData::Select(select) => Msg::SelectPreset(
+                                Preset::ALL.into_iter().find(|preset| preset.name() == select.value())
+                            ),
+                            _ => Msg::SelectPreset(None),
+                        }) }>
+                            { for Preset::ALL.iter().map(|preset| html! {
+                                <option value={ preset.name() }
+                                    selected={ !self.custom_selected && Preset::of(self.game.grid().config()) == Some(*preset) }>
+                                    { preset.name() }
+                                </option>
+                            }) }
+                            <option value="Custom"
+                                selected={ self.custom_selected || Preset::of(self.game.grid().config()).is_none() }>
+                                { "Custom" }
+                            </option>
+                        </select>
+                    </div>
+                    <div id="no-guess" onclick={ self.link.callback(|_| Msg::ToggleNoGuess ) }>
+                        <button>
+                        {
+                            if self.generation_policy == GenerationPolicy::NoGuess {
+                                "No-guess: on"
+                            } else {
+                                "No-guess: off"
+                            }
+                        }
+                        </button>
+                    </div>
+                    <div id="practice" title="Practice games are left out of the statistics and high scores"
+                        onclick={ self.link.callback(|_| Msg::TogglePractice ) }>
                         <button>
-                        { "Change difficulty" }
+                        { if self.practice { "Practice: on" } else { "Practice: off" } }
                         </button>
                     </div>
-                    <div id="flag" onclick={ self.link.callback(|_| Msg::ChangeFlag )}>
+                    {
+                        if self.practice {
+                            html! {
+                                <div id="odds" onclick={ self.link.callback(|_| Msg::ToggleOdds ) }>
+                                    <button>
+                                    { if self.show_odds { "Odds: on" } else { "Odds: off" } }
+                                    </button>
+                                </div>
+                            }
+                        } else {
+                            html! {}
+                        }
+                    }
+                    <div id="flag" title="Switch what a click does, for players without right-click or long press"
+                        onclick={ self.link.callback(|_| Msg::ChangeClickMode )}>
                         {
-                            match self.selected_flag {
-                                Flag::Tag => String::from("🚩"),
-                                Flag::Dig => String::from("⛏"),
+                            match self.click_mode {
+                                ClickMode::Flag => String::from("🚩"),
+                                ClickMode::Dig => String::from("⛏"),
                         }
                         }
                     </div>
+                    <div id="question-marks" onclick={ self.link.callback(|_| Msg::ToggleQuestionMarks ) }>
+                        <button>
+                        {
+                            if self.question_marks {
+                                "❓ marks: on"
+                            } else {
+                                "❓ marks: off"
+                            }
+                        }
+                        </button>
+                    </div>
+                    <div id="pause" onclick={ self.link.callback(|_| Msg::TogglePause ) }>
+                        <button disabled={ !matches!(self.game.status(), GameStatus::Playing | GameStatus::Paused) }>
+                        {
+                            if self.game.status() == GameStatus::Paused {
+                                "Resume"
+                            } else {
+                                "Pause"
+                            }
+                        }
+                        </button>
+                    </div>
+                    <div id="hint" title="Hints keep the game out of the best times"
+                        onclick={ self.link.callback(|_| Msg::Hint ) }>
+                        <button disabled={ self.game.status() != GameStatus::Playing
+                            || (self.daily.is_some() && self.daily_scored) }>
+                        { "Hint" }
+                        </button>
+                    </div>
+                    <div id="show-stats" onclick={ self.link.callback(|_| Msg::ToggleStats ) }>
+                        <button>
+                        { "Statistics" }
+                        </button>
+                    </div>
+                    <div id="show-keys" onclick={ self.link.callback(|_| Msg::ToggleKeys ) }>
+                        <button>
+                        { "Keys" }
+                        </button>
+                    </div>
                     <div id="timer">
-                        { self.elapsed_time }
+                        { format!("{:.1}", Seconds(self.game.elapsed_ms())) }
                     </div>
                 </div>
-                <div id="grid">
-                    <div class="column-container">
-                        { for (0..self.state.n_rows).map(|row| self.view_row(row)) }
+                {
+                    if self.game.grid().needs_guess() {
+                        html! {
+                            <div id="guess-warning" role="status">
+                                { "No layout without guesses was found in time, this board may need a guess." }
+                            </div>
+                        }
+                    } else {
+                        html! {}
+                    }
+                }
+                { self.view_hint() }
+                { self.view_result() }
+                { self.view_share() }
+                { self.view_saved_game_offer() }
+                { self.view_daily() }
+                { self.view_stats() }
+                { self.view_keys() }
+                { self.view_custom_form() }
+                <div id="replay">
+                    <input type="text" placeholder="Replay code"
+                        value={ self.replay_input.clone() }
+                        oninput={ self.link.callback(|e: InputData| Msg::ReplayInput(e.value)) }
+                    />
+                    <button onclick={ self.link.callback(|_| Msg::Replay ) }>
+                        { "Replay" }
+                    </button>
+                </div>
+                { self.view_grid() }
+                <div id="announcement" class="visually-hidden" role="status" aria-live="polite">
+                    { &self.announcement }
+                </div>
+            </>
+        }
+    }
+
+    /// Returns Html for the high score page, with a table per board configuration
+    fn view_high_scores(&self) -> Html {
+        let configs = self.high_scores.configs();
+        if configs.is_empty() {
+            return html! {
+                <div id="high-scores">{ "No high score yet, win a game to set one." }</div>
+            };
+        }
+        html! {
+            <div id="high-scores">
+                { for configs.into_iter().map(|config| html! {
+                    <table>
+                        <caption>
+                            {
+                                match Preset::of(config) {
+                                    Some(preset) => format!("{}: {}", preset.name(), config),
+                                    None => format!("Custom: {}", config),
+                                }
+                            }
+                        </caption>
+                        <tr>
+                            <th>{ "#" }</th>
+                            <th>{ "Name" }</th>
+                            <th>{ "Time" }</th>
+                            <th>{ "3BV" }</th>
+                            <th>{ "3BV/s" }</th>
+                            <th>{ "Date" }</th>
+                            <th>{ "Replay code" }</th>
+                        </tr>
+                        { for self.high_scores.table(config).iter().enumerate().map(|(rank, score)| html! {
+                            <tr>
+                                <td>{ rank + 1 }</td>
+                                <td>{ &score.name }</td>
+                                <td>{ Seconds(score.elapsed_ms) }</td>
+                                <td>{ score.three_bv }</td>
+                                <td>{ format!("{:.2}", three_bv_per_second(score.three_bv, score.elapsed_ms)) }</td>
+                                <td>{ format_date(score.date_ms) }</td>
+                                <td>{ score.replay.map(|code| code.to_string()).unwrap_or_default() }</td>
+                            </tr>
+                        }) }
+                    </table>
+                }) }
+            </div>
+        }
+    }
+
+    /// Starts a new game with `config` and the selected generation policy
+    fn restart(&mut self, config: GameConfig) {
+        self.start(Grid::new(config, self.generation_policy));
+    }
+
+    /// Starts a new game on `grid`, abandoning the current one
+    fn start(&mut self, grid: Grid) {
+        self.game = Game::new(grid, self.clock.clone());
+        self.daily = None;
+        self.shared_text = None;
+        self.hint = None;
+        self.cursor = (0, 0);
+        self.announcement = format!("New game, {}.", self.game.grid().config());
+        // the game in storage is the abandoned one, unless it is still offered to be resumed
+        if self.saved_game.is_none() {
+            if let Some(storage) = self.storage.as_mut() {
+                storage.remove(SAVED_GAME_KEY);
+            }
+        }
+        ConsoleService::log(&format!(
+            "{} mines in the grid.",
+            self.game.grid().mine_count()
+        ));
+    }
+
+    /// Digs the cell `idx`, and logs the grid once the first dig has laid its mines
+    fn dig(&mut self, idx: usize) -> ShouldRender {
+        ConsoleService::log(format!("Digging cell #{}.", idx).as_str());
+        let first_dig = !self.game.grid().mines_placed();
+        let outcome = self.game.dig(idx);
+        if first_dig && self.game.grid().mines_placed() {
+            ConsoleService::log(self.game.grid().to_string().as_str());
+        }
+        self.handle_outcome(outcome)
+    }
+
+    /// Returns the index of the cell under the keyboard cursor
+    fn cursor_idx(&self) -> usize {
+        let grid = self.game.grid();
+        Grid::xy_to_idx(self.cursor, grid.n_rows, grid.n_cols)
+            .expect("the cursor stays in the grid")
+    }
+
+    /// Counts the first dig of a daily challenge as the scored attempt of its day,
+    /// when it is the first attempt of the day
+    fn start_daily_attempt(&mut self) {
+        let challenge = match self.daily {
+            Some(challenge) if self.daily_scored => challenge,
+            _ => return,
+        };
+        if self.game.status() == GameStatus::NotStarted
+            || self.daily_log.entry(challenge.day()).is_some()
+        {
+            return;
+        }
+        if let Err(error) = self.daily_log.start(challenge.day()) {
+            ConsoleService::log(error.to_string().as_str());
+        }
+        self.store_daily_log();
+    }
+
+    /// Records the end of the scored attempt at a daily challenge
+    fn finish_daily(&mut self) {
+        let (challenge, result) = match (self.daily, self.game.result()) {
+            (Some(challenge), Some(result)) if self.daily_scored => (challenge, result),
+            _ => return,
+        };
+        if let Err(error) = self.daily_log.finish(challenge.day(), &result) {
+            ConsoleService::log(error.to_string().as_str());
+        }
+        self.store_daily_log();
+    }
+
+    fn store_daily_log(&mut self) {
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(DAILY_LOG_KEY, Ok(self.daily_log.to_json()));
+        }
+    }
+
+    /// Saves the game in progress to local storage, and forgets it once it is over.
+    /// A game not started yet leaves the saved one alone, so it can still be resumed.
+    fn persist_game(&mut self) {
+        if self.game.status() == GameStatus::NotStarted {
+            return;
+        }
+        // the saved game gets replaced, so it cannot be offered anymore
+        self.saved_game = None;
+        let storage = match self.storage.as_mut() {
+            Some(storage) => storage,
+            None => return,
+        };
+        if self.game.status().is_over() {
+            storage.remove(SAVED_GAME_KEY);
+        } else {
+            storage.store(
+                SAVED_GAME_KEY,
+                Ok(save::save(
+                    &self.game,
+                    self.practice,
+                    self.daily.map(|challenge| SavedDaily {
+                        challenge,
+                        scored: self.daily_scored,
+                    }),
+                )),
+            );
+        }
+    }
+
+    /// Computes the chances of a mine shown by the overlay, while it is shown
+    fn refresh_odds(&mut self) {
+        self.odds = if self.practice && self.show_odds && !self.game.status().is_over() {
+            mine_probabilities(&Board::from_grid(self.game.grid()))
+        } else {
+            None
+        };
+    }
+
+    /// Forgets the hint once its cell is played or the game is over
+    fn clear_played_hint(&mut self) {
+        let played = match &self.hint {
+            Some((idx, _)) => {
+                self.game.status().is_over()
+                    || !matches!(
+                        self.game.cell_view(*idx),
+                        CellView::Hidden | CellView::Marked(_)
+                    )
+            }
+            None => false,
+        };
+        if played {
+            self.hint = None;
+        }
+    }
+
+    /// Keeps the timer running exactly while the game is being played
+    fn sync_timer(&mut self) {
+        if self.game.status() != GameStatus::Playing {
+            // dropping the Interval stops it
+            self.timer_handle = None;
+        } else if self.timer_handle.is_none() {
+            let timer_link = self.link.clone();
+            self.timer_handle = Some(Interval::new(TIMER_REFRESH_DELAY, move || {
+                timer_link.send_message(Msg::RefreshTimer)
+            }));
+        }
+    }
+
+    /// Returns whether the current game goes into the statistics and high scores,
+    /// which practice games and unscored attempts at a daily challenge do not
+    fn is_recorded(&self) -> bool {
+        !self.practice && (self.daily.is_none() || self.daily_scored)
+    }
+
+    /// Adds the result of the game that just ended to the statistics
+    fn record_result(&mut self) {
+        if let Some(result) = self.game.result() {
+            self.stats.record(result);
+            self.store_stats();
+        }
+    }
+
+    /// Asks for a name and enters the game that was just won in the high scores,
+    /// when it is fast enough and was played without hints
+    fn record_high_score(&mut self) {
+        let result = match self.game.result() {
+            Some(result) if result.won && result.hints == 0 => result,
+            _ => return,
+        };
+        let rank = match self.high_scores.rank(result.config, result.elapsed_ms) {
+            Some(rank) => rank,
+            None => return,
+        };
+        let last_name = self
+            .storage
+            .as_ref()
+            .and_then(|storage| storage.restore::<Text>(PLAYER_NAME_KEY).ok());
+        let message = format!(
+            "New high score: #{} in {} seconds! Enter your name:",
+            rank + 1,
+            Seconds(result.elapsed_ms)
+        );
+        let name = match DialogService::prompt(&message, last_name.as_deref()) {
+            Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+            _ => return,
+        };
+        self.high_scores.insert(
+            result.config,
+            HighScore {
+                name: name.clone(),
+                elapsed_ms: result.elapsed_ms,
+                date_ms: js_sys::Date::now() as u64,
+                replay: Some(result.replay),
+                three_bv: result.three_bv,
+            },
+        );
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(HIGH_SCORES_KEY, Ok(self.high_scores.to_json()));
+            storage.store(PLAYER_NAME_KEY, Ok(name));
+        }
+    }
+
+    fn store_key_bindings(&mut self) {
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(KEY_BINDINGS_KEY, Ok(self.key_bindings.to_json()));
+        }
+    }
+
+    fn store_stats(&mut self) {
+        if let Some(storage) = self.storage.as_mut() {
+            storage.store(STATS_KEY, Ok(self.stats.to_json()));
+        }
+    }
+
+    fn log_result(&self) {
+        if let Some(result) = self.game.result() {
+            ConsoleService::log(&format!(
+                "Game {} in {} seconds.",
+                if result.won { "won" } else { "lost" },
+                Seconds(result.elapsed_ms)
+            ));
+        }
+    }
+
+    /// Logs what an action did, and notifies the end of the game.
+    /// Returns whether the view needs to be rendered again.
+    fn handle_outcome(&mut self, outcome: Outcome) -> ShouldRender {
+        if let Some(announcement) = announcement(&self.game, &outcome) {
+            self.announcement = announcement;
+        }
+        match outcome {
+            Outcome::Ignored => false,
+            Outcome::Revealed(count) => {
+                ConsoleService::log(format!("Newly revealed: {}", count).as_str());
+                true
+            }
+            Outcome::Marked(_) => true,
+            Outcome::Lost(mine_idx) => {
+                ConsoleService::log(format!("The mine in cell #{} exploded.", mine_idx).as_str());
+                self.link.send_message(Msg::Loss);
+                true
+            }
+            Outcome::Won => {
+                self.link.send_message(Msg::Win);
+                true
+            }
+        }
+    }
+
+    /// Returns Html offering to resume the game left unfinished by a previous visit
+    fn view_saved_game_offer(&self) -> Html {
+        let saved_game = match &self.saved_game {
+            Some(saved_game) => saved_game,
+            None => return html! {},
+        };
+        html! {
+            <div id="saved-game">
+                {
+                    format!(
+                        "Resume your unfinished game ({}, {:.1} s)?",
+                        saved_game.game.grid().config(),
+                        Seconds(saved_game.game.elapsed_ms())
+                    )
+                }
+                <button onclick={ self.link.callback(|_| Msg::ResumeSaved ) }>
+                    { "Resume" }
+                </button>
+                <button onclick={ self.link.callback(|_| Msg::DiscardSaved ) }>
+                    { "Discard" }
+                </button>
+            </div>
+        }
+    }
+
+    /// Returns Html for the statistics of every preset, with their export and import
+    fn view_stats(&self) -> Html {
+        if !self.show_stats {
+            return html! {};
+        }
+        let optional_time = |time_ms: Option<u64>| match time_ms {
+            Some(time_ms) => format!("{:.3}", Seconds(time_ms)),
+            None => String::from("-"),
+        };
+        let export_href = format!(
+            "data:application/json;charset=utf-8,{}",
+            String::from(js_sys::encode_uri_component(&self.stats.to_json()))
+        );
+        html! {
+            <div id="stats">
+                <table>
+                    <tr>
+                        <th>{ "Difficulty" }</th>
+                        <th>{ "Played" }</th>
+                        <th>{ "Win rate" }</th>
+                        <th>{ "Current streak" }</th>
+                        <th>{ "Best streak" }</th>
+                        <th>{ "Best time" }</th>
+                        <th>{ "Average time" }</th>
+                        <th>{ "Best 3BV/s" }</th>
+                        <th>{ "Average efficiency" }</th>
+                    </tr>
+                    { for Preset::ALL.iter().map(|preset| {
+                        let summary = self.stats.summary(preset.config());
+                        html! {
+                            <tr>
+                                <td>{ preset.name() }</td>
+                                <td>{ summary.played }</td>
+                                <td>
+                                    {
+                                        match summary.win_rate() {
+                                            Some(win_rate) => format!("{:.0}%", win_rate * 100.0),
+                                            None => String::from("-"),
+                                        }
+                                    }
+                                </td>
+                                <td>{ summary.current_streak }</td>
+                                <td>{ summary.best_streak }</td>
+                                <td>{ optional_time(summary.best_time_ms) }</td>
+                                <td>{ optional_time(summary.average_time_ms) }</td>
+                                <td>
+                                    {
+                                        match summary.best_three_bv_per_second {
+                                            Some(speed) => format!("{:.2}", speed),
+                                            None => String::from("-"),
+                                        }
+                                    }
+                                </td>
+                                <td>
+                                    {
+                                        match summary.average_efficiency {
+                                            Some(efficiency) => format!("{:.0}%", efficiency * 100.0),
+                                            None => String::from("-"),
+                                        }
+                                    }
+                                </td>
+                            </tr>
+                        }
+                    }) }
+                </table>
+                <a href={ export_href } download="minesweeper-statistics.json">
+                    { "Export" }
+                </a>
+                <label>
+                    { " Import " }
+                    <input type="file" accept=".json,application/json"
+                        onchange={ self.link.batch_callback(|change| match change {
+                            ChangeData::Files(files) => files.get(0).map(Msg::ImportStats),
+                            _ => None,
+                        }) }
+                    />
+                </label>
+                {
+                    match &self.stats_error {
+                        Some(error) => html! { <div id="stats-error">{ error }</div> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// Returns Html for the keys playing the game, each of which can be changed
+    fn view_keys(&self) -> Html {
+        if !self.show_keys {
+            return html! {};
+        }
+        html! {
+            <div id="keys" onkeydown={ self.link.callback(Msg::CaptureKey) }>
+                <table>
+                    { for KeyAction::ALL.iter().map(|action| {
+                        let action = *action;
+                        let keys: Vec<String> = self
+                            .key_bindings
+                            .keys(action)
+                            .into_iter()
+                            .map(key_label)
+                            .collect();
+                        html! {
+                            <tr>
+                                <td>{ action.name() }</td>
+                                <td>{ if keys.is_empty() { String::from("-") } else { keys.join(", ") } }</td>
+                                <td>
+                                    <button onclick={ self.link.callback(move |_| Msg::RebindKey(action)) }>
+                                        { if self.rebinding == Some(action) { "Press a key…" } else { "Change" } }
+                                    </button>
+                                </td>
+                            </tr>
+                        }
+                    }) }
+                </table>
+                <label>
+                    <input type="checkbox" checked={ self.key_bindings.vim_keys() }
+                        onclick={ self.link.callback(|_| Msg::ToggleVimKeys ) }
+                    />
+                    { " Vim-style keys (H, J, K, L)" }
+                </label>
+                <button onclick={ self.link.callback(|_| Msg::ResetKeys ) }>
+                    { "Restore the default keys" }
+                </button>
+            </div>
+        }
+    }
+
+    /// Returns Html for the hint last given, and how many hints the game used
+    fn view_hint(&self) -> Html {
+        if self.game.hints() == 0 {
+            return html! {};
+        }
+        html! {
+            <div id="hint-text">
+                {
+                    match &self.hint {
+                        Some((_, explanation)) => html! { <p>{ explanation }</p> },
+                        None => html! {},
+                    }
+                }
+                { format!("Hints used: {}", self.game.hints()) }
+            </div>
+        }
+    }
+
+    /// Returns Html for the measures of the finished game: the 3BV of its board,
+    /// how fast it was cleared, and how many clicks it took next to the ZiNi
+    fn view_result(&self) -> Html {
+        let result = match self.game.result() {
+            Some(result) => result,
+            None => return html! {},
+        };
+        html! {
+            <div id="result">
+                <span title="Least number of clicks clearing the board">
+                    { format!("3BV: {}", result.three_bv) }
+                </span>
+                {
+                    match result.three_bv_per_second() {
+                        Some(speed) => html! { <span>{ format!("3BV/s: {:.2}", speed) }</span> },
+                        None => html! {},
+                    }
+                }
+                <span>
+                    {
+                        format!(
+                            "Clicks: {} ({} left, {} right, {} chord)",
+                            result.clicks.total(),
+                            result.clicks.left,
+                            result.clicks.right,
+                            result.clicks.chord
+                        )
+                    }
+                </span>
+                <span title="About the least number of clicks clearing the board with flags and chords">
+                    { format!("ZiNi: {}", result.zini) }
+                </span>
+                {
+                    match result.efficiency() {
+                        Some(efficiency) => html! {
+                            <span title="3BV divided by the clicks">
+                                { format!("Efficiency: {:.0}%", efficiency * 100.0) }
+                            </span>
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// Returns Html for sharing the result of a finished game
+    fn view_share(&self) -> Html {
+        if !self.game.status().is_over() {
+            return html! {};
+        }
+        html! {
+            <div id="share">
+                <button onclick={ self.link.callback(|_| Msg::Share ) }>
+                    { "Share" }
+                </button>
+                {
+                    match &self.shared_text {
+                        Some(text) => html! {
+                            <>
+                                { " Copied to the clipboard:" }
+                                <pre id="share-text">{ text }</pre>
+                            </>
+                        },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// Returns Html for the daily challenge: how today went, the streak, and a
+    /// button to play it
+    fn view_daily(&self) -> Html {
+        let today = DailyChallenge::new(today());
+        let status = match self.daily_log.entry(today.day()) {
+            None => String::from("not played yet"),
+            Some(entry) => match entry.elapsed_ms {
+                None if self.daily == Some(today) => String::from("in progress"),
+                None => String::from("abandoned"),
+                Some(elapsed_ms) if entry.won => format!("won in {} s", Seconds(elapsed_ms)),
+                Some(elapsed_ms) => format!("lost after {} s", Seconds(elapsed_ms)),
+            },
+        };
+        html! {
+            <div id="daily">
+                <button onclick={ self.link.callback(|_| Msg::StartDaily ) }>
+                    { "Daily challenge" }
+                </button>
+                { format!(" {}: {}, streak {}", today.date, status, self.daily_log.streak(today.day())) }
+                {
+                    match (self.daily.is_some() && !self.daily_scored, self.practice) {
+                        (true, true) => html! { <div id="daily-unscored">{ "Practice: this attempt is not scored." }</div> },
+                        (true, false) => html! { <div id="daily-unscored">{ "Already played today: this attempt is not scored, nor recorded in the statistics." }</div> },
+                        _ => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// Returns Html for the custom configuration form, shown when "Custom" is picked
+    fn view_custom_form(&self) -> Html {
+        if !self.custom_selected {
+            return html! {};
+        }
+        html! {
+            <div id="custom-config">
+                <label>
+                    { "Rows " }
+                    <input type="number" min="1" max={ GameConfig::MAX_ROWS.to_string() }
+                        value={ self.custom_rows.clone() }
+                        oninput={ self.link.callback(|e: InputData| Msg::CustomInput((CustomField::Rows, e.value))) }
+                    />
+                </label>
+                <label>
+                    { "Columns " }
+                    <input type="number" min="1" max={ GameConfig::MAX_COLS.to_string() }
+                        value={ self.custom_cols.clone() }
+                        oninput={ self.link.callback(|e: InputData| Msg::CustomInput((CustomField::Columns, e.value))) }
+                    />
+                </label>
+                <label>
+                    { "Mines " }
+                    <input type="number" min="0" value={ self.custom_mines.clone() }
+                        oninput={ self.link.callback(|e: InputData| Msg::CustomInput((CustomField::Mines, e.value))) }
+                    />
+                </label>
+                <button onclick={ self.link.callback(|_| Msg::ApplyCustom ) }>
+                    { "Start" }
+                </button>
+                {
+                    match &self.config_error {
+                        Some(error) => html! { <div id="config-error">{ error }</div> },
+                        None => html! {},
+                    }
+                }
+            </div>
+        }
+    }
+
+    /// Returns Html for the board, which takes the keys once it has the focus,
+    /// with the grid hidden behind a notice while the game is paused
+    ///
+    /// Both keep the same focused element, which is an ARIA grid whose active
+    /// cell follows the keyboard cursor.
+    fn view_grid(&self) -> Html {
+        let onkeydown = self.link.callback(Msg::KeyDown);
+        let onfocus = self.link.callback(|_: FocusEvent| Msg::BoardFocus(true));
+        let onblur = self.link.callback(|_: FocusEvent| Msg::BoardFocus(false));
+        if self.game.status() == GameStatus::Paused {
+            return html! {
+                <div id="board" tabindex="0" role="region" aria-label="Paused game"
+                    onkeydown={ onkeydown } onfocus={ onfocus } onblur={ onblur }
+                >
+                    <div id="paused" onclick={ self.link.callback(|_| Msg::TogglePause ) }>
+                        { "Paused, click to resume" }
+                    </div>
+                </div>
+            };
+        }
+        let grid = self.game.grid();
+        html! {
+            <div id="board" tabindex="0" role="grid" aria-label="Minefield"
+                aria-rowcount={ grid.n_rows.to_string() }
+                aria-colcount={ grid.n_cols.to_string() }
+                aria-activedescendant={ format!("cell-{}", self.cursor_idx()) }
+                onkeydown={ onkeydown } onfocus={ onfocus } onblur={ onblur }
+            >
+                <div id="grid" role="presentation">
+                    <div class="column-container" role="rowgroup">
+                        { for (0..grid.n_rows).map(|row| self.view_row(row)) }
                     </div>
                 </div>
             </div>
         }
     }
-}
 
-impl Model {
     /// Returns Html for a single grid cell
     pub fn view_cell(&self, cell_idx: usize) -> Html {
+        let cell_view = self.game.cell_view(cell_idx);
+        let (content, class) = match cell_view {
+            // a daily challenge or a replayed board starts from the cell its mines were laid around
+            CellView::Hidden
+                if self.game.status() == GameStatus::NotStarted
+                    && self.game.grid().first_dig() == Some(cell_idx) =>
+            {
+                (String::new(), "cell start")
+            }
+            CellView::Hidden => (String::new(), "cell"),
+            CellView::Marked(Flag::Tag) => (String::from("🚩"), "cell"),
+            CellView::Marked(Flag::Question) => (String::from("❓"), "cell"),
+            CellView::Revealed(cnt) => (format!("{}", cnt), "cell revealed"),
+            CellView::Mine => (String::from("💣"), "cell mine"),
+            CellView::ExplodedMine => (String::from("💥"), "cell exploded"),
+            CellView::WrongFlag => (String::from("❌"), "cell wrong-flag"),
+            CellView::CorrectFlag => (String::from("🚩"), "cell correct-flag"),
+        };
+        // the overlay shades the cells left to play from green when safe to red when mined
+        let chance = match (cell_view, &self.odds) {
+            (CellView::Hidden | CellView::Marked(_), Some(odds)) => odds[cell_idx],
+            _ => None,
+        };
+        let (style, title) = match chance {
+            Some(chance) => (
+                format!(
+                    "background-color: hsl({:.0}, 80%, 70%)",
+                    120.0 * (1.0 - chance)
+                ),
+                format!("{:.0}% chance of a mine", chance * 100.0),
+            ),
+            None => (String::new(), String::new()),
+        };
+        let mut class = String::from(class);
+        if matches!(self.hint, Some((idx, _)) if idx == cell_idx) {
+            class.push_str(" hint");
+        }
+        if self.board_focused && cell_idx == self.cursor_idx() {
+            class.push_str(" cursor");
+        }
         html! {
-            <div class="cell" id={ format!("cell-{}", cell_idx) }
+            <div class={ class } id={ format!("cell-{}", cell_idx) }
+                style={ style } title={ title }
+                role="gridcell"
+                aria-colindex={ (cell_idx % self.game.grid().n_cols + 1).to_string() }
+                aria-label={ cell_label(&self.game, cell_idx) }
                 onclick={ self.link.callback(move |event| Msg::Clicked((cell_idx, event))) }
-            >
-                {
-                    if self.play_status == GameStatus::Lost || self.state.grid_vec[cell_idx].is_clicked {
-                        match self.state.grid_vec[cell_idx].data {
-                            CellData::Mine => String::from("💣"),
-                            CellData::MineNeighbor(cnt) => format!("{}", cnt),
-                        }
+                oncontextmenu={ self.link.callback(move |event: MouseEvent| {
+                    event.prevent_default();
+                    Msg::CycleFlag(cell_idx)
+                }) }
+                ontouchstart={ self.link.callback(move |_: TouchEvent| Msg::TouchStart(cell_idx)) }
+                ontouchend={ self.link.callback(|_: TouchEvent| Msg::TouchEnd) }
+                ontouchmove={ self.link.callback(|_: TouchEvent| Msg::TouchEnd) }
+                ontouchcancel={ self.link.callback(|_: TouchEvent| Msg::TouchEnd) }
+                onmousedown={ self.link.batch_callback(move |event: MouseEvent| {
+                    // a middle click, or pressing both buttons together, chords the cell
+                    if event.button() == 1 {
+                        event.prevent_default();
+                        Some(Msg::Chord(cell_idx))
+                    } else if event.buttons() == 3 {
+                        event.prevent_default();
+                        Some(Msg::BothButtonsChord(cell_idx))
                     } else {
-                        match self.state.grid_vec[cell_idx].flag {
-                            Some(Flag::Tag) => String::from("🚩"),
-                            Some(Flag::Dig) => String::from("⛏"),
-                            None => String::from("❓"),
-                        }
+                        Some(Msg::MouseDown)
                     }
-                }
+                }) }
+            >
+                { content }
             </div>
         }
     }
@@ -261,10 +1684,10 @@ impl Model {
     /// Returns Html for a row of cells
     pub fn view_row(&self, row_idx: usize) -> Html {
         html! {
-            <div class="row-container">
-                { for (0..self.state.n_cols)
+            <div class="row-container" role="row" aria-rowindex={ (row_idx + 1).to_string() }>
+                { for (0..self.game.grid().n_cols)
                         .map(|col| Grid::xy_to_idx((row_idx, col),
-                            self.state.n_rows, self.state.n_cols))
+                            self.game.grid().n_rows, self.game.grid().n_cols))
                         .map(|idx| self.view_cell(idx.unwrap()))
                 }
             </div>
@@ -0,0 +1,525 @@
+use std::collections::{HashMap, VecDeque};
+
+use crate::solver::Board;
+use crate::state::Grid;
+
+/// Unrevealed cells linked to each other through the numbers they border,
+/// with how their mines can be laid out
+struct Component {
+    cells: Vec<usize>,
+    /// Number of layouts satisfying the numbers, by number of mines in the component
+    layouts: Vec<f64>,
+    /// Number of those layouts mining each cell, by number of mines in the component
+    mined: Vec<Vec<f64>>,
+}
+
+/// A revealed number, as the positions of its unrevealed neighbors in a component
+struct LocalConstraint {
+    cells: Vec<usize>,
+    mines: usize,
+}
+
+/// Most states counted for a board before `mine_probabilities` gives up, which
+/// keeps it to a fraction of a second. Expert boards in play take a few thousand.
+const MAX_STATES: usize = 100_000;
+
+/// How one cell of a component changes the state of `count_layouts`
+struct Step {
+    /// The numbers around the cell: where their mines so far are in the state,
+    /// unless they start at this cell, how many mines they hold, and how many of
+    /// their cells come after this one
+    checks: Vec<(Option<usize>, usize, usize)>,
+    /// The numbers of the next state: where their mines so far are in this state,
+    /// unless they start at this cell, and whether they are around the cell
+    next: Vec<(Option<usize>, bool)>,
+}
+
+impl Step {
+    /// Returns the state following `state` once the cell is mined or not, or
+    /// `None` when that breaks a number
+    fn apply(&self, state: &[u8], mined: bool) -> Option<Vec<u8>> {
+        let placed = |source: Option<usize>| source.map_or(0, |at| usize::from(state[at]));
+        for (source, mines, after) in &self.checks {
+            let placed = placed(*source) + usize::from(mined);
+            if placed > *mines || placed + after < *mines {
+                return None;
+            }
+        }
+        Some(
+            self.next
+                .iter()
+                .map(|(source, around)| (placed(*source) + usize::from(mined && *around)) as u8)
+                .collect(),
+        )
+    }
+}
+
+/// Adds `counts`, shifted by `shift` mines, to `total`
+fn add_shifted(total: &mut Vec<f64>, counts: &[f64], shift: usize) {
+    if total.len() < counts.len() + shift {
+        total.resize(counts.len() + shift, 0.0);
+    }
+    for (mines, count) in counts.iter().enumerate() {
+        total[mines + shift] += count;
+    }
+}
+
+/// Counts the layouts of a component of `n_cells` cells, taking its cells in turn
+///
+/// Layouts of the first cells that put as many mines around each number with cells
+/// on both sides of the turn are completed alike, so they are counted together as
+/// one state, by number of mines. Counts are carried forward from the first cell,
+/// then completions backward from the last one, and the layouts mining a cell are
+/// those reaching it in a state, times those completing it from there.
+///
+/// Returns `None` once more states are needed than are left in `budget`.
+fn count_layouts(
+    n_cells: usize,
+    constraints: &[LocalConstraint],
+    budget: &mut usize,
+) -> Option<Component> {
+    let first = |c: &LocalConstraint| *c.cells.iter().min().expect("numbers border cells");
+    let last = |c: &LocalConstraint| *c.cells.iter().max().expect("numbers border cells");
+    // the numbers with cells on both sides of each turn, before every cell and after the last
+    let mut open: Vec<Vec<usize>> = vec![Vec::new(); n_cells + 1];
+    for (id, constraint) in constraints.iter().enumerate() {
+        for turn in open
+            .iter_mut()
+            .take(last(constraint) + 1)
+            .skip(first(constraint) + 1)
+        {
+            turn.push(id);
+        }
+    }
+    let steps: Vec<Step> = (0..n_cells)
+        .map(|position| {
+            let source = |id: usize| open[position].iter().position(|open_id| *open_id == id);
+            Step {
+                checks: constraints
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, c)| c.cells.contains(&position))
+                    .map(|(id, c)| {
+                        let after = c.cells.iter().filter(|cell| **cell > position).count();
+                        (source(id), c.mines, after)
+                    })
+                    .collect(),
+                next: open[position + 1]
+                    .iter()
+                    .map(|id| (source(*id), constraints[*id].cells.contains(&position)))
+                    .collect(),
+            }
+        })
+        .collect();
+
+    // the layouts of the cells before each turn, by state and number of mines
+    let mut forward: Vec<HashMap<Vec<u8>, Vec<f64>>> = vec![HashMap::from([(vec![], vec![1.0])])];
+    for step in &steps {
+        let mut next: HashMap<Vec<u8>, Vec<f64>> = HashMap::new();
+        for (state, counts) in forward.last().expect("the first turn is counted") {
+            for mined in [false, true] {
+                if let Some(next_state) = step.apply(state, mined) {
+                    add_shifted(
+                        next.entry(next_state).or_default(),
+                        counts,
+                        usize::from(mined),
+                    );
+                }
+            }
+        }
+        *budget = budget.checked_sub(next.len())?;
+        forward.push(next);
+    }
+
+    // the completions of the cells from each turn on, by state and number of mines
+    let mut backward: HashMap<Vec<u8>, Vec<f64>> = HashMap::from([(vec![], vec![1.0])]);
+    let mut mined = vec![vec![0.0; n_cells]; n_cells + 1];
+    for (position, step) in steps.iter().enumerate().rev() {
+        let mut completions: HashMap<Vec<u8>, Vec<f64>> = HashMap::new();
+        for (state, counts) in &forward[position] {
+            for is_mined in [false, true] {
+                let after = match step
+                    .apply(state, is_mined)
+                    .and_then(|next_state| backward.get(&next_state))
+                {
+                    Some(after) => after,
+                    None => continue,
+                };
+                add_shifted(
+                    completions.entry(state.clone()).or_default(),
+                    after,
+                    usize::from(is_mined),
+                );
+                if is_mined {
+                    for (before_mines, before) in counts.iter().enumerate() {
+                        for (after_mines, after) in after.iter().enumerate() {
+                            mined[before_mines + 1 + after_mines][position] += before * after;
+                        }
+                    }
+                }
+            }
+        }
+        backward = completions;
+    }
+    let mut layouts = backward.remove(&vec![]).unwrap_or_default();
+    layouts.resize(n_cells + 1, 0.0);
+    Some(Component {
+        cells: Vec::new(),
+        layouts,
+        mined,
+    })
+}
+
+/// Returns the distribution of the total number of mines of `components`,
+/// counted in layouts
+fn convolve<'a>(components: impl Iterator<Item = &'a Component>) -> Vec<f64> {
+    let mut total = vec![1.0];
+    for component in components {
+        let mut next = vec![0.0; total.len() + component.layouts.len() - 1];
+        for (mines, count) in total.iter().enumerate() {
+            for (component_mines, layouts) in component.layouts.iter().enumerate() {
+                next[mines + component_mines] += count * layouts;
+            }
+        }
+        total = next;
+    }
+    total
+}
+
+/// Returns the chance of every unrevealed cell of `board` to hold a mine,
+/// `None` for revealed cells, or `None` altogether when no layout fits the board
+/// or when counting the layouts takes more than `MAX_STATES` states
+///
+/// The unrevealed cells bordering numbers are split into independent components,
+/// whose layouts are counted by `count_layouts`. The other cells share the mines left by each
+/// layout, which weighs the layouts by the number of ways to place those mines,
+/// so that the probabilities are exact for the mine count of the board.
+pub fn mine_probabilities(board: &Board) -> Option<Vec<Option<f64>>> {
+    probabilities_within(board, MAX_STATES)
+}
+
+fn probabilities_within(board: &Board, mut budget: usize) -> Option<Vec<Option<f64>>> {
+    let n_cells = board.numbers.len();
+    let is_hidden = |idx: usize| board.numbers[idx].is_none();
+    let mut constraints: Vec<(Vec<usize>, usize)> = Vec::new();
+    let mut cell_constraints: Vec<Vec<usize>> = vec![Vec::new(); n_cells];
+    for (idx, number) in board.numbers.iter().enumerate() {
+        let number = match number {
+            Some(number) => *number,
+            None => continue,
+        };
+        let cells: Vec<usize> = Grid::valid_neighbor_indices(idx, board.n_rows, board.n_cols)
+            .into_iter()
+            .filter(|nidx| is_hidden(*nidx))
+            .collect();
+        if number > cells.len() {
+            return None;
+        }
+        if cells.is_empty() {
+            continue;
+        }
+        for cell in &cells {
+            cell_constraints[*cell].push(constraints.len());
+        }
+        constraints.push((cells, number));
+    }
+
+    // gathers the components in breadth-first order, which keeps
+    // few numbers open at each turn of `count_layouts`
+    let mut component_of: Vec<Option<usize>> = vec![None; n_cells];
+    let mut components: Vec<Component> = Vec::new();
+    for first in (0..n_cells).filter(|idx| !cell_constraints[*idx].is_empty()) {
+        if component_of[first].is_some() {
+            continue;
+        }
+        let id = components.len();
+        let mut cells = Vec::new();
+        let mut to_visit = VecDeque::from([first]);
+        component_of[first] = Some(id);
+        while let Some(cell) = to_visit.pop_front() {
+            cells.push(cell);
+            for c in &cell_constraints[cell] {
+                for next in &constraints[*c].0 {
+                    if component_of[*next].is_none() {
+                        component_of[*next] = Some(id);
+                        to_visit.push_back(*next);
+                    }
+                }
+            }
+        }
+        let position = |cell: usize| cells.iter().position(|c| *c == cell).unwrap();
+        let mut is_local = vec![false; constraints.len()];
+        let mut local: Vec<LocalConstraint> = Vec::new();
+        for cell in &cells {
+            for c in &cell_constraints[*cell] {
+                if !is_local[*c] {
+                    is_local[*c] = true;
+                    local.push(LocalConstraint {
+                        cells: constraints[*c]
+                            .0
+                            .iter()
+                            .map(|cell| position(*cell))
+                            .collect(),
+                        mines: constraints[*c].1,
+                    });
+                }
+            }
+        }
+        let mut component = count_layouts(cells.len(), &local, &mut budget)?;
+        component.cells = cells;
+        components.push(component);
+    }
+
+    let interior: Vec<usize> = (0..n_cells)
+        .filter(|idx| is_hidden(*idx) && cell_constraints[*idx].is_empty())
+        .collect();
+    // ln(k!) for every k up to the number of cells
+    let ln_factorials: Vec<f64> = (0..=n_cells)
+        .scan(0.0, |sum, k| {
+            if k > 0 {
+                *sum += (k as f64).ln();
+            }
+            Some(*sum)
+        })
+        .collect();
+    let ln_choose = |n: usize, k: usize| ln_factorials[n] - ln_factorials[k] - ln_factorials[n - k];
+    let n_frontier_cells = components.iter().map(|c| c.cells.len()).sum::<usize>();
+    let interior_mines = |frontier: usize| {
+        board
+            .n_mines
+            .checked_sub(frontier)
+            .filter(|mines| *mines <= interior.len())
+    };
+    // the ways to place the mines left on the interior, scaled down
+    // by the largest of them so that they cannot overflow
+    let scale = (0..=n_frontier_cells)
+        .filter_map(|frontier| interior_mines(frontier).map(|m| ln_choose(interior.len(), m)))
+        .fold(f64::NEG_INFINITY, f64::max);
+    let interior_ways = |frontier: usize| match interior_mines(frontier) {
+        Some(mines) => (ln_choose(interior.len(), mines) - scale).exp(),
+        None => 0.0,
+    };
+
+    let all = convolve(components.iter());
+    let total: f64 = all
+        .iter()
+        .enumerate()
+        .map(|(mines, layouts)| layouts * interior_ways(mines))
+        .sum();
+    if total == 0.0 {
+        return None;
+    }
+
+    let mut probabilities: Vec<Option<f64>> = (0..n_cells)
+        .map(|idx| if is_hidden(idx) { Some(0.0) } else { None })
+        .collect();
+    for (id, component) in components.iter().enumerate() {
+        let others = convolve(
+            components
+                .iter()
+                .enumerate()
+                .filter(|(other, _)| *other != id)
+                .map(|(_, other)| other),
+        );
+        for (mines, mined) in component.mined.iter().enumerate() {
+            let weight: f64 = others
+                .iter()
+                .enumerate()
+                .map(|(other_mines, layouts)| layouts * interior_ways(mines + other_mines))
+                .sum();
+            for (position, count) in mined.iter().enumerate() {
+                if let Some(p) = probabilities[component.cells[position]].as_mut() {
+                    *p += count * weight / total;
+                }
+            }
+        }
+    }
+    if !interior.is_empty() {
+        let expected: f64 = all
+            .iter()
+            .enumerate()
+            .filter_map(|(mines, layouts)| {
+                interior_mines(mines).map(|left| layouts * interior_ways(mines) * left as f64)
+            })
+            .sum();
+        for idx in &interior {
+            probabilities[*idx] = Some(expected / total / interior.len() as f64);
+        }
+    }
+    Some(probabilities)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::solver::{deduce, Verdict};
+    use crate::state::{CellData, GenerationPolicy};
+    use rand::seq::IteratorRandom;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    fn assert_probabilities(actual: Option<Vec<Option<f64>>>, expected: &[Option<f64>]) {
+        let actual = actual.expect("the board has layouts");
+        assert_eq!(actual.len(), expected.len());
+        for (idx, (actual, expected)) in actual.iter().zip(expected).enumerate() {
+            match (actual, expected) {
+                (Some(actual), Some(expected)) => assert!(
+                    (actual - expected).abs() < 1e-9,
+                    "cell #{}: {} instead of {}",
+                    idx,
+                    actual,
+                    expected
+                ),
+                _ => assert_eq!(actual, expected, "cell #{}", idx),
+            }
+        }
+    }
+
+    #[test]
+    fn test_nothing_revealed() {
+        assert_probabilities(
+            mine_probabilities(&Board::hidden(2, 2, 1)),
+            &[Some(0.25); 4],
+        );
+    }
+
+    #[test]
+    /// A 1 in the middle of a 3x4 grid holds exactly one of its 8 neighbors,
+    /// which leaves the other mine to the 3 cells of the right column
+    fn test_interior_cells() {
+        let probabilities = mine_probabilities(&Board::from_layout(
+            3,
+            4,
+            2,
+            "# # # #
+             # 1 # #
+             # # # #",
+        ));
+        let (frontier, interior) = (Some(1.0 / 8.0), Some(1.0 / 3.0));
+        assert_probabilities(
+            probabilities,
+            &[
+                frontier, frontier, frontier, interior, frontier, None, frontier, interior,
+                frontier, frontier, frontier, interior,
+            ],
+        );
+    }
+
+    #[test]
+    /// The two 1s share a mine in the middle cell, or have one each on their
+    /// outer cells. With one mine left for two interior cells, the first layout
+    /// can be completed in two ways, and the second in one way only.
+    fn test_mine_count_weighs_layouts() {
+        assert_probabilities(
+            mine_probabilities(&Board::from_layout(1, 7, 2, "# 1 # 1 # # #")),
+            &[
+                Some(1.0 / 3.0),
+                None,
+                Some(2.0 / 3.0),
+                None,
+                Some(1.0 / 3.0),
+                Some(1.0 / 3.0),
+                Some(1.0 / 3.0),
+            ],
+        );
+        assert_probabilities(
+            mine_probabilities(&Board::from_layout(1, 5, 1, "# 1 # 1 #")),
+            &[Some(0.0), None, Some(1.0), None, Some(0.0)],
+        );
+    }
+
+    #[test]
+    fn test_independent_components() {
+        assert_probabilities(
+            mine_probabilities(&Board::from_layout(1, 5, 2, "1 # # 1 #")),
+            &[None, Some(1.0), Some(0.5), None, Some(0.5)],
+        );
+    }
+
+    #[test]
+    /// On a real board, the chances add up to the mine count, and agree with
+    /// every deduction of the solver
+    fn test_agrees_with_the_solver() {
+        for seed in 0..5 {
+            let mut grid =
+                Grid::from_seed(GameConfig::EXPERT, GenerationPolicy::SafeFirstClick, seed);
+            grid.dig(240);
+            let board = Board::from_grid(&grid);
+            let probabilities = mine_probabilities(&board).unwrap();
+            let sum: f64 = probabilities.iter().flatten().sum();
+            assert!((sum - grid.mine_count() as f64).abs() < 1e-6);
+            for deduction in deduce(&board) {
+                let expected = match deduction.verdict {
+                    Verdict::Safe => 0.0,
+                    Verdict::Mine => 1.0,
+                };
+                assert!((probabilities[deduction.idx].unwrap() - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    /// An Expert board opened by random digs, with a frontier of close to 200 cells,
+    /// once too many layouts to list one by one
+    fn test_large_frontier() {
+        let mut grid = Grid::from_seed(GameConfig::EXPERT, GenerationPolicy::SafeFirstClick, 39);
+        grid.dig(240);
+        let mut rng = ChaCha8Rng::seed_from_u64(9);
+        for _ in 0..27 {
+            let safe_idx = (0..grid.grid_vec.len())
+                .filter(|idx| {
+                    let cell = &grid.grid_vec[*idx];
+                    !cell.is_clicked && cell.data != CellData::Mine
+                })
+                .choose(&mut rng)
+                .unwrap();
+            grid.dig(safe_idx);
+        }
+        let board = Board::from_grid(&grid);
+        let frontier = (0..grid.grid_vec.len())
+            .filter(|idx| {
+                board.numbers[*idx].is_none()
+                    && Grid::valid_neighbor_indices(*idx, grid.n_rows, grid.n_cols)
+                        .iter()
+                        .any(|nidx| board.numbers[*nidx].is_some())
+            })
+            .count();
+        assert!(frontier > 150, "{} cells on the frontier", frontier);
+
+        let probabilities = mine_probabilities(&board).unwrap();
+        let sum: f64 = probabilities.iter().flatten().sum();
+        assert!((sum - grid.mine_count() as f64).abs() < 1e-6);
+        // the same board gives up within a budget too small for it
+        assert_eq!(probabilities_within(&board, 100), None);
+    }
+
+    #[test]
+    /// The states of every component count against the same budget: the cells
+    /// of the first board go through 2, 2 and 1 states, and the two components
+    /// of the second board through 1 state, then 2 and 1.
+    fn test_budget() {
+        for (board, states) in [
+            (Board::from_layout(1, 7, 2, "# 1 # 1 # # #"), 5),
+            (Board::from_layout(1, 5, 2, "1 # # 1 #"), 4),
+        ] {
+            assert_eq!(
+                probabilities_within(&board, states),
+                mine_probabilities(&board)
+            );
+            assert_eq!(probabilities_within(&board, states - 1), None);
+        }
+    }
+
+    #[test]
+    fn test_no_layout_fits() {
+        assert_eq!(
+            mine_probabilities(&Board::from_layout(1, 3, 1, "# 2 #")),
+            None
+        );
+        assert_eq!(
+            mine_probabilities(&Board::from_layout(1, 3, 2, "# 0 #")),
+            None
+        );
+    }
+}
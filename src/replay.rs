@@ -0,0 +1,245 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::config::{ConfigError, GameConfig};
+use crate::state::{GenerationPolicy, Grid};
+
+/// Everything that lays out the mines of a board again: its configuration, its
+/// generation policy, its seed, and the cell dug first when the layout depends on it
+///
+/// Written like `16x30x99-s240-1234`: the dimensions and mines, the policy as `i`,
+/// `s` or `n` followed by the first dug cell, and the seed.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct ReplayCode {
+    pub config: GameConfig,
+    pub policy: GenerationPolicy,
+    pub seed: u64,
+    /// The cell the mines were laid out around, `None` with `GenerationPolicy::Immediate`
+    pub start: Option<usize>,
+}
+
+/// Reasons for a replay code to be rejected
+#[derive(Eq, PartialEq, Debug)]
+pub enum ReplayCodeError {
+    /// The text does not follow the format of a replay code
+    Malformed,
+    Config(ConfigError),
+    /// The first dug cell is missing, unexpected, or outside of the grid
+    Start,
+}
+
+impl Display for ReplayCodeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReplayCodeError::Malformed => write!(
+                f,
+                "A replay code looks like 16x30x99-s240-1234: size, policy and first cell, seed."
+            ),
+            ReplayCodeError::Config(error) => error.fmt(f),
+            ReplayCodeError::Start => {
+                write!(
+                    f,
+                    "The first cell of the replay code does not fit the grid."
+                )
+            }
+        }
+    }
+}
+
+impl ReplayCode {
+    /// Returns the code of `grid`, once its layout is known: a deferred policy
+    /// needs the first dig
+    pub fn of(grid: &Grid) -> Option<Self> {
+        let start = match grid.policy() {
+            GenerationPolicy::Immediate => None,
+            _ => Some(grid.first_dig()?),
+        };
+        Some(ReplayCode {
+            config: grid.config(),
+            policy: grid.policy(),
+            seed: grid.seed(),
+            start,
+        })
+    }
+
+    /// Returns the board of the code, with its mines placed
+    pub fn grid(&self) -> Grid {
+        let mut grid = Grid::from_seed(self.config, self.policy, self.seed);
+        if let Some(start) = self.start {
+            grid.prepare_dig(start);
+        }
+        grid
+    }
+}
+
+impl Display for ReplayCode {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let policy = match self.policy {
+            GenerationPolicy::Immediate => "i",
+            GenerationPolicy::SafeFirstClick => "s",
+            GenerationPolicy::NoGuess => "n",
+        };
+        write!(
+            f,
+            "{}x{}x{}-{}{}-{}",
+            self.config.n_rows(),
+            self.config.n_cols(),
+            self.config.n_mines(),
+            policy,
+            self.start
+                .map(|start| start.to_string())
+                .unwrap_or_default(),
+            self.seed
+        )
+    }
+}
+
+impl FromStr for ReplayCode {
+    type Err = ReplayCodeError;
+
+    fn from_str(code: &str) -> Result<Self, Self::Err> {
+        let number = |text: &str| {
+            text.parse::<usize>()
+                .map_err(|_| ReplayCodeError::Malformed)
+        };
+        let parts: Vec<&str> = code.trim().split('-').collect();
+        let (size, start, seed) = match parts.as_slice() {
+            [size, start, seed] => (*size, *start, *seed),
+            _ => return Err(ReplayCodeError::Malformed),
+        };
+        let size = size
+            .split('x')
+            .map(number)
+            .collect::<Result<Vec<usize>, _>>()?;
+        let config = match size.as_slice() {
+            [n_rows, n_cols, n_mines] => {
+                GameConfig::new(*n_rows, *n_cols, *n_mines).map_err(ReplayCodeError::Config)?
+            }
+            _ => return Err(ReplayCodeError::Malformed),
+        };
+        let policy = match start.get(..1) {
+            Some("i") => GenerationPolicy::Immediate,
+            Some("s") => GenerationPolicy::SafeFirstClick,
+            Some("n") => GenerationPolicy::NoGuess,
+            _ => return Err(ReplayCodeError::Malformed),
+        };
+        let start = match &start[1..] {
+            "" => None,
+            cell => Some(number(cell)?),
+        };
+        let start_fits = match start {
+            Some(start) => policy != GenerationPolicy::Immediate && start < config.n_cells(),
+            None => policy == GenerationPolicy::Immediate,
+        };
+        if !start_fits {
+            return Err(ReplayCodeError::Start);
+        }
+        Ok(ReplayCode {
+            config,
+            policy,
+            seed: seed.parse().map_err(|_| ReplayCodeError::Malformed)?,
+            start,
+        })
+    }
+}
+
+/// Replay codes are stored as they are written, see `Display`
+impl Serialize for ReplayCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for ReplayCode {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let code = String::deserialize(deserializer)?;
+        code.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replays_the_layout_of_the_first_dig() {
+        for policy in [GenerationPolicy::SafeFirstClick, GenerationPolicy::NoGuess] {
+            let mut grid = Grid::from_seed(GameConfig::INTERMEDIATE, policy, 42);
+            grid.dig(100);
+            let code = ReplayCode::of(&grid).unwrap();
+            assert_eq!(code.start, Some(100));
+            let replayed: ReplayCode = code.to_string().parse().unwrap();
+            assert_eq!(replayed, code);
+            assert_eq!(replayed.grid().to_string(), grid.to_string());
+        }
+    }
+
+    #[test]
+    fn test_codes() {
+        let grid = Grid::from_seed(GameConfig::EXPERT, GenerationPolicy::Immediate, 1234);
+        let code = ReplayCode::of(&grid).unwrap();
+        assert_eq!(code.to_string(), "16x30x99-i-1234");
+        assert_eq!(code.grid().to_string(), grid.to_string());
+        // the layout of a deferred policy is not known before the first dig
+        let grid = Grid::from_seed(GameConfig::EXPERT, GenerationPolicy::NoGuess, 1234);
+        assert_eq!(ReplayCode::of(&grid), None);
+        assert_eq!(
+            "9x9x10-s40-7".parse(),
+            Ok(ReplayCode {
+                config: GameConfig::BEGINNER,
+                policy: GenerationPolicy::SafeFirstClick,
+                seed: 7,
+                start: Some(40),
+            })
+        );
+    }
+
+    #[test]
+    fn test_rejected_codes() {
+        for (code, error) in [
+            ("1234", ReplayCodeError::Malformed),
+            ("9x9-s40-7", ReplayCodeError::Malformed),
+            ("9x9x10-q40-7", ReplayCodeError::Malformed),
+            ("9x9x10-s40-x", ReplayCodeError::Malformed),
+            (
+                "9x9x81-s40-7",
+                ReplayCodeError::Config(ConfigError::TooManyMines {
+                    n_mines: 81,
+                    n_cells: 81,
+                }),
+            ),
+            (
+                "101x9x10-s40-7",
+                ReplayCodeError::Config(ConfigError::TooLarge {
+                    max_rows: GameConfig::MAX_ROWS,
+                    max_cols: GameConfig::MAX_COLS,
+                }),
+            ),
+            (
+                "18446744073709551615x2x1-s0-7",
+                ReplayCodeError::Config(ConfigError::TooLarge {
+                    max_rows: GameConfig::MAX_ROWS,
+                    max_cols: GameConfig::MAX_COLS,
+                }),
+            ),
+            ("9x9x10-s81-7", ReplayCodeError::Start),
+            ("9x9x10-s-7", ReplayCodeError::Start),
+            ("9x9x10-i40-7", ReplayCodeError::Start),
+        ] {
+            assert_eq!(code.parse::<ReplayCode>(), Err(error), "{}", code);
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let code: ReplayCode = "9x9x10-n40-7".parse().unwrap();
+        let json = serde_json::to_string(&code).unwrap();
+        assert_eq!(json, "\"9x9x10-n40-7\"");
+        assert_eq!(serde_json::from_str::<ReplayCode>(&json).unwrap(), code);
+        assert!(serde_json::from_str::<ReplayCode>("\"9x9x81-n40-7\"").is_err());
+        assert!(serde_json::from_str::<ReplayCode>("7").is_err());
+    }
+}
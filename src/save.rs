@@ -0,0 +1,575 @@
+use std::fmt::{Display, Formatter};
+use std::ops::RangeInclusive;
+use std::rc::Rc;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::clock::Clock;
+use crate::config::GameConfig;
+use crate::daily::{CalendarDate, DailyChallenge};
+use crate::game::{Clicks, Game, GameStatus};
+use crate::state::{Cell, CellData, Flag, GenerationPolicy, Grid};
+
+/// Version written and read by `save` and `load`. Changes to the saved types
+/// go into a new version, and games saved in another one are not resumed.
+pub const SAVE_VERSION: u32 = 1;
+
+/// Reasons for saved data, such as a game or statistics, to be rejected
+#[derive(Eq, PartialEq, Debug)]
+pub enum LoadError {
+    /// The text does not follow the saved format at all
+    Malformed(String),
+    /// The data was saved by a version this one does not know about
+    UnsupportedVersion(u32),
+    /// The data does not describe something that could have been played
+    Inconsistent(&'static str),
+}
+
+impl Display for LoadError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LoadError::Malformed(reason) => write!(f, "The saved data is malformed: {}", reason),
+            LoadError::UnsupportedVersion(version) => {
+                write!(f, "Data saved by version {} is not supported.", version)
+            }
+            LoadError::Inconsistent(reason) => {
+                write!(f, "The saved data is inconsistent: {}.", reason)
+            }
+        }
+    }
+}
+
+/// Everything stored, such as a game or statistics, is wrapped in an envelope
+/// telling which version of its format the rest follows
+#[derive(Serialize, Deserialize)]
+struct Envelope<T> {
+    version: u32,
+    #[serde(flatten)]
+    data: T,
+}
+
+/// Returns `data` as versioned JSON, its fields next to the version,
+/// to be read back by `read_envelope`
+pub(crate) fn write_envelope<T: Serialize>(version: u32, data: T) -> String {
+    serde_json::to_string(&Envelope { version, data }).expect("stored data always serializes")
+}
+
+/// Reads JSON written by `write_envelope` in one of `versions`, and returns
+/// its version with its data, which is left to the caller to check
+pub(crate) fn read_envelope<T: DeserializeOwned>(
+    json: &str,
+    versions: RangeInclusive<u32>,
+) -> Result<(u32, T), LoadError> {
+    let malformed = |error: serde_json::Error| LoadError::Malformed(error.to_string());
+    let envelope: Envelope<Value> = serde_json::from_str(json).map_err(malformed)?;
+    if !versions.contains(&envelope.version) {
+        return Err(LoadError::UnsupportedVersion(envelope.version));
+    }
+    let data = serde_json::from_value(envelope.data).map_err(malformed)?;
+    Ok((envelope.version, data))
+}
+
+/// The data of a saved game, in the type of its version
+#[derive(Serialize, Deserialize)]
+struct Saved<T> {
+    game: T,
+}
+
+/// The types of version 1, kept apart from the engine's so that the engine can
+/// change without breaking the games already saved
+#[derive(Serialize, Deserialize)]
+struct GameV1 {
+    n_rows: usize,
+    n_cols: usize,
+    n_mines: usize,
+    policy: PolicyV1,
+    seed: u64,
+    mines_placed: bool,
+    /// The cell the mines were laid out around
+    first_dig: Option<usize>,
+    /// Whether the no-guess search gave up on the layout
+    needs_guess: bool,
+    status: StatusV1,
+    elapsed_ms: u64,
+    clicks: ClicksV1,
+    hints: usize,
+    practice: bool,
+    /// The daily challenge the game is an attempt at
+    daily: Option<DailyV1>,
+    cells: Vec<CellV1>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DailyV1 {
+    year: i32,
+    month: u32,
+    day: u32,
+    scored: bool,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ClicksV1 {
+    left: usize,
+    right: usize,
+    chord: usize,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CellV1 {
+    is_clicked: bool,
+    flag: Option<FlagV1>,
+    data: CellDataV1,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CellDataV1 {
+    Mine,
+    MineNeighbor(usize),
+}
+
+#[derive(Serialize, Deserialize)]
+enum FlagV1 {
+    Tag,
+    Question,
+}
+
+#[derive(Serialize, Deserialize)]
+enum PolicyV1 {
+    Immediate,
+    SafeFirstClick,
+    NoGuess,
+}
+
+#[derive(Serialize, Deserialize)]
+enum StatusV1 {
+    NotStarted,
+    Playing,
+    Paused,
+    Lost,
+    Won,
+}
+
+/// A game read back by `load`, with how it was being played
+pub struct SavedGame {
+    pub game: Game,
+    /// Practice games are left out of the statistics and high scores
+    pub practice: bool,
+    pub daily: Option<SavedDaily>,
+}
+
+/// The daily challenge a saved game is an attempt at
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub struct SavedDaily {
+    pub challenge: DailyChallenge,
+    /// Whether the attempt is the scored one of its day
+    pub scored: bool,
+}
+
+/// Returns `game` as versioned JSON, to be read back by `load`
+pub fn save(game: &Game, practice: bool, daily: Option<SavedDaily>) -> String {
+    let grid = game.grid();
+    let config = grid.config();
+    let clicks = game.clicks();
+    let saved = GameV1 {
+        n_rows: config.n_rows(),
+        n_cols: config.n_cols(),
+        n_mines: config.n_mines(),
+        policy: match grid.policy() {
+            GenerationPolicy::Immediate => PolicyV1::Immediate,
+            GenerationPolicy::SafeFirstClick => PolicyV1::SafeFirstClick,
+            GenerationPolicy::NoGuess => PolicyV1::NoGuess,
+        },
+        seed: grid.seed(),
+        mines_placed: grid.mines_placed(),
+        first_dig: grid.first_dig(),
+        needs_guess: grid.needs_guess(),
+        status: match game.status() {
+            GameStatus::NotStarted => StatusV1::NotStarted,
+            GameStatus::Playing => StatusV1::Playing,
+            GameStatus::Paused => StatusV1::Paused,
+            GameStatus::Lost => StatusV1::Lost,
+            GameStatus::Won => StatusV1::Won,
+        },
+        elapsed_ms: game.elapsed_ms(),
+        clicks: ClicksV1 {
+            left: clicks.left,
+            right: clicks.right,
+            chord: clicks.chord,
+        },
+        hints: game.hints(),
+        practice,
+        daily: daily.map(|daily| DailyV1 {
+            year: daily.challenge.date.year,
+            month: daily.challenge.date.month,
+            day: daily.challenge.date.day,
+            scored: daily.scored,
+        }),
+        cells: grid
+            .grid_vec
+            .iter()
+            .map(|cell| CellV1 {
+                is_clicked: cell.is_clicked,
+                flag: cell.flag.map(|flag| match flag {
+                    Flag::Tag => FlagV1::Tag,
+                    Flag::Question => FlagV1::Question,
+                }),
+                data: match cell.data {
+                    CellData::Mine => CellDataV1::Mine,
+                    CellData::MineNeighbor(count) => CellDataV1::MineNeighbor(count),
+                },
+            })
+            .collect(),
+    };
+    write_envelope(SAVE_VERSION, Saved { game: saved })
+}
+
+/// Reads a game written by `save`, timed by `clock`
+///
+/// The game is checked to be one that could have been played: a game saved while
+/// in progress comes back paused.
+pub fn load(json: &str, clock: Rc<dyn Clock>) -> Result<SavedGame, LoadError> {
+    let (_, Saved { game: saved }): (u32, Saved<GameV1>) =
+        read_envelope(json, SAVE_VERSION..=SAVE_VERSION)?;
+    let config = GameConfig::new(saved.n_rows, saved.n_cols, saved.n_mines)
+        .map_err(|_| LoadError::Inconsistent("the configuration is not playable"))?;
+    if saved.cells.len() != config.n_cells() {
+        return Err(LoadError::Inconsistent("the cells do not fill the grid"));
+    }
+    let grid_vec: Vec<Cell> = saved
+        .cells
+        .into_iter()
+        .map(|cell| Cell {
+            is_clicked: cell.is_clicked,
+            flag: cell.flag.map(|flag| match flag {
+                FlagV1::Tag => Flag::Tag,
+                FlagV1::Question => Flag::Question,
+            }),
+            data: match cell.data {
+                CellDataV1::Mine => CellData::Mine,
+                CellDataV1::MineNeighbor(count) => CellData::MineNeighbor(count),
+            },
+        })
+        .collect();
+    if grid_vec.iter().any(|c| c.is_clicked && c.flag.is_some()) {
+        return Err(LoadError::Inconsistent("a revealed cell carries a marker"));
+    }
+    if saved.mines_placed {
+        check_mines(&grid_vec, config)?;
+    } else if grid_vec
+        .iter()
+        .any(|c| c.is_clicked || c.data != CellData::MineNeighbor(0))
+    {
+        return Err(LoadError::Inconsistent(
+            "cells are revealed before the mines are placed",
+        ));
+    }
+
+    let policy = match saved.policy {
+        PolicyV1::Immediate => GenerationPolicy::Immediate,
+        PolicyV1::SafeFirstClick => GenerationPolicy::SafeFirstClick,
+        PolicyV1::NoGuess => GenerationPolicy::NoGuess,
+    };
+    if saved
+        .first_dig
+        .is_some_and(|idx| !saved.mines_placed || idx >= config.n_cells())
+    {
+        return Err(LoadError::Inconsistent("the first dig is not on the grid"));
+    }
+    // the mines of a deferred policy are laid out around the first dig
+    if policy != GenerationPolicy::Immediate && saved.mines_placed && saved.first_dig.is_none() {
+        return Err(LoadError::Inconsistent(
+            "the mines were placed without a first dig",
+        ));
+    }
+    // only a no-guess search that ran can have given up
+    if saved.needs_guess && (policy != GenerationPolicy::NoGuess || !saved.mines_placed) {
+        return Err(LoadError::Inconsistent(
+            "only a no-guess layout can need a guess",
+        ));
+    }
+    let grid = Grid::from_parts(
+        config,
+        policy,
+        saved.seed,
+        grid_vec,
+        saved.mines_placed,
+        saved.first_dig,
+        saved.needs_guess,
+    );
+    let status = match saved.status {
+        StatusV1::NotStarted => GameStatus::NotStarted,
+        StatusV1::Playing => GameStatus::Playing,
+        StatusV1::Paused => GameStatus::Paused,
+        StatusV1::Lost => GameStatus::Lost,
+        StatusV1::Won => GameStatus::Won,
+    };
+    let started = grid.grid_vec.iter().any(|c| c.is_clicked);
+    let consistent = match status {
+        GameStatus::NotStarted => !started,
+        GameStatus::Playing | GameStatus::Paused => {
+            started && !grid.is_exploded() && !grid.is_cleared()
+        }
+        GameStatus::Lost => grid.is_exploded(),
+        GameStatus::Won => grid.is_cleared() && !grid.is_exploded(),
+    };
+    if !consistent {
+        return Err(LoadError::Inconsistent(
+            "the status does not match the grid",
+        ));
+    }
+    let clicks = Clicks {
+        left: saved.clicks.left,
+        right: saved.clicks.right,
+        chord: saved.clicks.chord,
+    };
+    let game = Game::restore(grid, status, saved.elapsed_ms, clicks, saved.hints, clock);
+    let daily = match saved.daily {
+        Some(daily) => Some(load_daily(daily, &game)?),
+        None => None,
+    };
+    Ok(SavedGame {
+        game,
+        practice: saved.practice,
+        daily,
+    })
+}
+
+/// Returns the daily challenge of `game`, checked to be the board being played
+fn load_daily(saved: DailyV1, game: &Game) -> Result<SavedDaily, LoadError> {
+    let challenge = DailyChallenge::new(CalendarDate {
+        year: saved.year,
+        month: saved.month,
+        day: saved.day,
+    });
+    let grid = game.grid();
+    if grid.config() != DailyChallenge::CONFIG
+        || grid.policy() != DailyChallenge::POLICY
+        || grid.seed() != challenge.seed()
+        || grid.first_dig() != Some(challenge.start)
+    {
+        return Err(LoadError::Inconsistent(
+            "the board is not the one of its daily challenge",
+        ));
+    }
+    Ok(SavedDaily {
+        challenge,
+        scored: saved.scored,
+    })
+}
+
+/// Checks that the grid holds as many mines as configured, and that every
+/// number counts the mines around it
+fn check_mines(grid_vec: &[Cell], config: GameConfig) -> Result<(), LoadError> {
+    let is_mine = |idx: usize| grid_vec[idx].data == CellData::Mine;
+    if (0..grid_vec.len()).filter(|idx| is_mine(*idx)).count() != config.n_mines() {
+        return Err(LoadError::Inconsistent(
+            "the number of mines does not match the configuration",
+        ));
+    }
+    let numbers_match = (0..grid_vec.len()).all(|idx| match grid_vec[idx].data {
+        CellData::Mine => true,
+        CellData::MineNeighbor(count) => {
+            Grid::valid_neighbor_indices(idx, config.n_rows(), config.n_cols())
+                .into_iter()
+                .filter(|nidx| is_mine(*nidx))
+                .count()
+                == count
+        }
+    });
+    if !numbers_match {
+        return Err(LoadError::Inconsistent(
+            "a number does not count the mines around it",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::game::Outcome;
+
+    fn clock() -> Rc<dyn Clock> {
+        Rc::new(ManualClock::default())
+    }
+
+    /// Returns a beginner game with a few moves played
+    fn game_in_progress(clock: Rc<dyn Clock>) -> Game {
+        let mut game = Game::new(
+            Grid::from_seed(GameConfig::BEGINNER, GenerationPolicy::NoGuess, 7),
+            clock,
+        );
+        assert!(matches!(game.dig(40), Outcome::Revealed(_)));
+        let hidden: Vec<usize> = (0..81)
+            .filter(|idx| !game.grid().grid_vec[*idx].is_clicked)
+            .collect();
+        game.cycle_flag(hidden[0], true);
+        game.cycle_flag(hidden[1], true);
+        game.cycle_flag(hidden[1], true);
+        game.hint().unwrap();
+        game
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let manual_clock = Rc::new(ManualClock::default());
+        let game = game_in_progress(manual_clock.clone());
+        manual_clock.advance(4321);
+        let json = save(&game, true, None);
+        let saved = load(&json, clock()).unwrap();
+        assert!(saved.practice);
+        let loaded = saved.game;
+
+        assert_eq!(loaded.status(), GameStatus::Paused);
+        assert_eq!(loaded.elapsed_ms(), 4321);
+        assert_eq!(loaded.grid().config(), GameConfig::BEGINNER);
+        assert_eq!(loaded.grid().seed(), 7);
+        assert_eq!(loaded.grid().policy(), GenerationPolicy::NoGuess);
+        assert_eq!(
+            loaded.clicks(),
+            Clicks {
+                left: 1,
+                right: 3,
+                chord: 0,
+            }
+        );
+        assert_eq!(loaded.hints(), 1);
+        assert_eq!(loaded.grid().first_dig(), Some(40));
+        assert_eq!(loaded.grid().to_string(), game.grid().to_string());
+        for idx in 0..81 {
+            assert_eq!(loaded.cell_view(idx), game.cell_view(idx));
+        }
+        // saving again is stable, apart from the status
+        assert_eq!(
+            save(&loaded, true, None),
+            json.replace("\"Playing\"", "\"Paused\"")
+        );
+    }
+
+    #[test]
+    fn test_game_not_started_places_the_same_mines() {
+        let game = Game::new(
+            Grid::from_seed(GameConfig::BEGINNER, GenerationPolicy::SafeFirstClick, 3),
+            clock(),
+        );
+        let mut loaded = load(&save(&game, false, None), clock()).unwrap().game;
+        let mut game = game;
+        assert_eq!(loaded.status(), GameStatus::NotStarted);
+        game.dig(10);
+        loaded.dig(10);
+        assert_eq!(loaded.grid().to_string(), game.grid().to_string());
+    }
+
+    #[test]
+    /// A no-guess search that gave up is remembered, rather than found again
+    fn test_needs_guess_is_kept() {
+        let config = GameConfig::new(16, 30, 200).unwrap();
+        let mut game = Game::new(
+            Grid::from_seed(config, GenerationPolicy::NoGuess, 3),
+            clock(),
+        );
+        game.dig(240);
+        assert!(game.grid().needs_guess());
+        let json = save(&game, false, None);
+        let loaded = load(&json, clock()).unwrap().game;
+        assert!(loaded.grid().needs_guess());
+
+        // a layout placed right away was never searched
+        let game = Game::new(
+            Grid::from_seed(config, GenerationPolicy::Immediate, 3),
+            clock(),
+        );
+        let json =
+            save(&game, false, None).replacen("\"needs_guess\":false", "\"needs_guess\":true", 1);
+        assert!(matches!(
+            load(&json, clock()),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+
+    #[test]
+    fn test_daily_challenge() {
+        let challenge = DailyChallenge::new(CalendarDate {
+            year: 2024,
+            month: 2,
+            day: 29,
+        });
+        let mut game = Game::new(challenge.grid(), clock());
+        game.dig(challenge.start);
+        let daily = SavedDaily {
+            challenge,
+            scored: true,
+        };
+        let saved = load(&save(&game, false, Some(daily)), clock()).unwrap();
+        assert_eq!(saved.daily, Some(daily));
+
+        // another day has another board
+        let other_day = SavedDaily {
+            challenge: DailyChallenge::new(CalendarDate {
+                year: 2024,
+                month: 3,
+                day: 1,
+            }),
+            scored: true,
+        };
+        assert!(matches!(
+            load(&save(&game, false, Some(other_day)), clock()),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_version() {
+        let json = save(&game_in_progress(clock()), false, None).replacen(
+            "\"version\":1",
+            "\"version\":99",
+            1,
+        );
+        assert_eq!(
+            load(&json, clock()).err(),
+            Some(LoadError::UnsupportedVersion(99))
+        );
+    }
+
+    #[test]
+    fn test_malformed() {
+        assert!(matches!(
+            load("not json", clock()),
+            Err(LoadError::Malformed(_))
+        ));
+        assert!(matches!(
+            load("{\"version\":1,\"game\":{}}", clock()),
+            Err(LoadError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_inconsistent() {
+        let json = save(&game_in_progress(clock()), false, None);
+        let mut value: Value = serde_json::from_str(&json).unwrap();
+
+        let mut wrong_status = value.clone();
+        wrong_status["game"]["status"] = Value::from("Won");
+        let mut wrong_size = value.clone();
+        wrong_size["game"]["n_rows"] = Value::from(10);
+        let mut wrong_mines = value.clone();
+        wrong_mines["game"]["n_mines"] = Value::from(11);
+
+        let cells = value["game"]["cells"].as_array_mut().unwrap();
+        let idx = cells
+            .iter()
+            .position(|cell| cell["data"] == "Mine")
+            .unwrap();
+        cells[idx]["data"] = serde_json::json!({ "MineNeighbor": 0 });
+
+        for wrong in [wrong_status, wrong_size, wrong_mines, value] {
+            assert!(matches!(
+                load(&wrong.to_string(), clock()),
+                Err(LoadError::Inconsistent(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,169 @@
+use crate::clock::Seconds;
+use crate::config::Preset;
+use crate::game::{CellView, Game};
+use crate::state::{Flag, Grid};
+
+/// Widest and tallest the emoji map of a shared result gets, in emojis
+pub const MAP_MAX_SIZE: usize = 12;
+
+/// Returns the emoji of a cell, and how much it matters when several cells
+/// are shrunk into a single emoji of the map
+fn map_emoji(view: CellView) -> (u8, &'static str) {
+    match view {
+        CellView::ExplodedMine => (5, "💥"),
+        CellView::WrongFlag => (4, "❌"),
+        CellView::Mine => (3, "💣"),
+        CellView::CorrectFlag | CellView::Marked(Flag::Tag) => (2, "🚩"),
+        CellView::Hidden | CellView::Marked(Flag::Question) => (1, "⬜"),
+        CellView::Revealed(_) => (0, "🟩"),
+    }
+}
+
+/// Returns an emoji map of the board, one line per row
+///
+/// Boards larger than `MAP_MAX_SIZE` are shrunk by drawing square blocks of cells
+/// as their most telling cell, so that an explosion is never left out.
+pub fn emoji_map(game: &Game) -> String {
+    let grid = game.grid();
+    let block = [grid.n_rows, grid.n_cols]
+        .iter()
+        .map(|size| size.div_ceil(MAP_MAX_SIZE))
+        .max()
+        .unwrap_or(1)
+        .max(1);
+    let mut map = String::new();
+    for block_row in (0..grid.n_rows).step_by(block) {
+        for block_col in (0..grid.n_cols).step_by(block) {
+            let (_, emoji) = (block_row..(block_row + block).min(grid.n_rows))
+                .flat_map(|row| {
+                    (block_col..(block_col + block).min(grid.n_cols)).map(move |col| (row, col))
+                })
+                .filter_map(|xy| Grid::xy_to_idx(xy, grid.n_rows, grid.n_cols))
+                .map(|idx| map_emoji(game.cell_view(idx)))
+                .max_by_key(|(importance, _)| *importance)
+                .expect("a block holds at least one cell");
+            map.push_str(emoji);
+        }
+        map.push('\n');
+    }
+    map
+}
+
+/// Returns a short text telling how a finished game went, to be shared,
+/// or `None` while the game is not over
+///
+/// Efficiency is the 3BV of the board divided by the clicks it took, see
+/// `crate::game::efficiency`, and is left out for a lost game, which did not
+/// clear that 3BV. The board is given by its replay code.
+pub fn share_text(game: &Game) -> Option<String> {
+    let result = game.result()?;
+    let difficulty = match Preset::of(result.config) {
+        Some(preset) => preset.name(),
+        None => "Custom",
+    };
+    let efficiency = match result.efficiency() {
+        Some(efficiency) => format!(", efficiency {:.0}%", efficiency * 100.0),
+        None => String::new(),
+    };
+    Some(format!(
+        "Minesweeper {} ({}), replay {}\n{} {} in {} s\n3BV {}, {} clicks{}\n{}",
+        difficulty,
+        result.config,
+        result.replay,
+        if result.won { "😎" } else { "🤯" },
+        if result.won { "Won" } else { "Lost" },
+        Seconds(result.elapsed_ms),
+        result.three_bv,
+        result.clicks.total(),
+        efficiency,
+        emoji_map(game)
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::config::GameConfig;
+    use crate::game::Outcome;
+    use crate::state::{CellData, GenerationPolicy};
+    use std::rc::Rc;
+
+    #[test]
+    fn test_nothing_to_share_while_playing() {
+        let game = Game::new(
+            Grid::from_layout(1, 3, "* . ."),
+            Rc::new(ManualClock::default()),
+        );
+        assert_eq!(share_text(&game), None);
+    }
+
+    #[test]
+    fn test_lost_game() {
+        let clock = Rc::new(ManualClock::default());
+        let mut game = Game::new(
+            Grid::from_layout(
+                2,
+                3,
+                "* . *
+                 . . .",
+            ),
+            clock.clone(),
+        );
+        game.cycle_flag(2, true);
+        game.cycle_flag(3, true);
+        game.dig(5);
+        clock.advance(1500);
+        assert_eq!(game.dig(0), Outcome::Lost(0));
+        assert_eq!(
+            share_text(&game).unwrap(),
+            "Minesweeper Custom (2x3, 2 mines), replay 2x3x2-i-0\n\
+             🤯 Lost in 1.500 s\n\
+             3BV 4, 4 clicks\n\
+             💥⬜🚩\n\
+             ❌⬜🟩\n"
+        );
+    }
+
+    #[test]
+    fn test_won_game() {
+        let mut game = Game::new(
+            Grid::from_layout(
+                3,
+                3,
+                "* . .
+                 . . .
+                 . . .",
+            ),
+            Rc::new(ManualClock::default()),
+        );
+        game.dig(1);
+        game.dig(8);
+        let text = share_text(&game).unwrap();
+        assert!(text.contains("😎 Won in 0.000 s"));
+        assert!(text.contains("3BV 1, 2 clicks, efficiency 50%"));
+        assert!(text.ends_with("🚩🟩🟩\n🟩🟩🟩\n🟩🟩🟩\n"));
+    }
+
+    #[test]
+    fn test_large_boards_are_shrunk() {
+        let mut game = Game::new(
+            Grid::from_seed(GameConfig::EXPERT, GenerationPolicy::SafeFirstClick, 5),
+            Rc::new(ManualClock::default()),
+        );
+        game.dig(0);
+        let mine = (0..GameConfig::EXPERT.n_cells())
+            .find(|idx| game.grid().grid_vec[*idx].data == CellData::Mine)
+            .unwrap();
+        assert_eq!(game.dig(mine), Outcome::Lost(mine));
+        assert!(share_text(&game)
+            .unwrap()
+            .starts_with("Minesweeper Expert (16x30, 99 mines), replay 16x30x99-s0-5\n"));
+        let map = emoji_map(&game);
+        assert_eq!(map.matches('💥').count(), 1);
+        let lines: Vec<&str> = map.lines().collect();
+        // blocks of 3x3 cells
+        assert_eq!(lines.len(), 6);
+        assert!(lines.iter().all(|line| line.chars().count() == 10));
+    }
+}
@@ -0,0 +1,574 @@
+use std::collections::BTreeSet;
+
+use crate::state::{CellData, Grid};
+
+/// What the player sees of a grid: its size, its number of mines, and the numbers
+/// of the revealed cells. Where the mines are is never part of it.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Board {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub n_mines: usize,
+    /// The number of mined neighbors of each revealed cell, `None` while it is not revealed
+    pub numbers: Vec<Option<usize>>,
+}
+
+impl Board {
+    /// Returns what the player sees of `grid`
+    pub fn from_grid(grid: &Grid) -> Self {
+        Board {
+            n_rows: grid.n_rows,
+            n_cols: grid.n_cols,
+            n_mines: grid.mine_count(),
+            numbers: grid
+                .grid_vec
+                .iter()
+                .map(|cell| match cell.data {
+                    CellData::MineNeighbor(count) if cell.is_clicked => Some(count),
+                    _ => None,
+                })
+                .collect(),
+        }
+    }
+
+    /// Returns a board of `n_rows` by `n_cols` cells with nothing revealed yet
+    pub fn hidden(n_rows: usize, n_cols: usize, n_mines: usize) -> Self {
+        Board {
+            n_rows,
+            n_cols,
+            n_mines,
+            numbers: vec![None; n_rows * n_cols],
+        }
+    }
+
+    /// Returns a board drawn as rows of the numbers of the revealed cells and `#`
+    /// for the others, separated by whitespace
+    #[cfg(test)]
+    pub fn from_layout(n_rows: usize, n_cols: usize, n_mines: usize, layout: &str) -> Self {
+        Board {
+            n_rows,
+            n_cols,
+            n_mines,
+            numbers: layout
+                .split_whitespace()
+                .map(|cell| cell.parse().ok())
+                .collect(),
+        }
+    }
+}
+
+/// Where a constraint comes from
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Origin {
+    /// The number of the revealed cell at this index
+    Number(usize),
+    /// The number of mines left in the whole grid
+    MineCount,
+}
+
+/// `mines` of the `cells` are mined, where `cells` are neither revealed nor deduced yet
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Constraint {
+    pub origin: Origin,
+    pub cells: BTreeSet<usize>,
+    pub mines: usize,
+}
+
+/// What a deduction tells of a cell
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Verdict {
+    Safe,
+    Mine,
+}
+
+/// The reasoning a deduction follows
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum Rule {
+    /// A constraint has no mine left, or as many mines as cells
+    Single,
+    /// A constraint is contained in another one, so the cells only in the larger one
+    /// hold the difference of their mines
+    Subset,
+    /// Two constraints share cells, and the mines the shared cells can hold
+    /// settle the cells of one of them
+    Overlap,
+}
+
+/// A cell whose content is certain, with the constraints it follows from,
+/// as they stood when it was deduced
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Deduction {
+    pub idx: usize,
+    pub verdict: Verdict,
+    pub rule: Rule,
+    pub reasons: Vec<Constraint>,
+}
+
+/// Deduces which cells of a `Board` are certainly safe or mined, the way a player
+/// would, one round of deductions after the other
+///
+/// Each round applies the single-cell rule, and only when it finds nothing, compares
+/// the constraints two by two. Cells deduced safe stay unrevealed until `reveal`
+/// gives their number, which opens the way to more deductions.
+pub struct Solver {
+    board: Board,
+    verdicts: Vec<Option<Verdict>>,
+    deductions: Vec<Deduction>,
+}
+
+impl Solver {
+    pub fn new(board: Board) -> Self {
+        Solver {
+            verdicts: vec![None; board.numbers.len()],
+            deductions: Vec::new(),
+            board,
+        }
+    }
+
+    pub fn board(&self) -> &Board {
+        &self.board
+    }
+
+    pub fn verdict(&self, idx: usize) -> Option<Verdict> {
+        self.verdicts[idx]
+    }
+
+    /// Returns every deduction made so far, in the order they were made
+    pub fn deductions(&self) -> &[Deduction] {
+        &self.deductions
+    }
+
+    /// Records the number of a cell that was just revealed
+    pub fn reveal(&mut self, idx: usize, number: usize) {
+        self.board.numbers[idx] = Some(number);
+    }
+
+    /// Deduces everything the board allows, and returns the new deductions
+    pub fn solve(&mut self) -> &[Deduction] {
+        let first_new = self.deductions.len();
+        loop {
+            let constraints = self.constraints();
+            let mut found = single_deductions(&constraints);
+            if found.is_empty() {
+                found = pair_deductions(&constraints);
+            }
+            if found.is_empty() {
+                break;
+            }
+            for deduction in found {
+                // a cell settled by several constraints keeps the first reasons found
+                if self.verdicts[deduction.idx].is_none() {
+                    self.verdicts[deduction.idx] = Some(deduction.verdict);
+                    self.deductions.push(deduction);
+                }
+            }
+        }
+        &self.deductions[first_new..]
+    }
+
+    fn is_unknown(&self, idx: usize) -> bool {
+        self.board.numbers[idx].is_none() && self.verdicts[idx].is_none()
+    }
+
+    fn mines_known(&self, cells: impl Iterator<Item = usize>) -> usize {
+        cells
+            .filter(|idx| self.verdicts[*idx] == Some(Verdict::Mine))
+            .count()
+    }
+
+    /// Returns a constraint for every revealed number bordering unknown cells,
+    /// followed by the one of the mine count
+    fn constraints(&self) -> Vec<Constraint> {
+        let board = &self.board;
+        let mut constraints: Vec<Constraint> = (0..board.numbers.len())
+            .filter_map(|idx| {
+                let number = board.numbers[idx]?;
+                let neighbors = Grid::valid_neighbor_indices(idx, board.n_rows, board.n_cols);
+                let mines_known = self.mines_known(neighbors.iter().copied());
+                let cells: BTreeSet<usize> = neighbors
+                    .into_iter()
+                    .filter(|nidx| self.is_unknown(*nidx))
+                    .collect();
+                if cells.is_empty() {
+                    return None;
+                }
+                Some(Constraint {
+                    origin: Origin::Number(idx),
+                    cells,
+                    // a board contradicting itself gives no constraint
+                    mines: number.checked_sub(mines_known)?,
+                })
+            })
+            .collect();
+        let cells: BTreeSet<usize> = (0..board.numbers.len())
+            .filter(|idx| self.is_unknown(*idx))
+            .collect();
+        let mines_left = board
+            .n_mines
+            .checked_sub(self.mines_known(0..board.numbers.len()));
+        if let (false, Some(mines)) = (cells.is_empty(), mines_left) {
+            constraints.push(Constraint {
+                origin: Origin::MineCount,
+                cells,
+                mines,
+            });
+        }
+        constraints
+    }
+}
+
+/// Settles the constraints with no mine left, or with as many mines as cells
+fn single_deductions(constraints: &[Constraint]) -> Vec<Deduction> {
+    let mut found = Vec::new();
+    for constraint in constraints {
+        let verdict = if constraint.mines == 0 {
+            Verdict::Safe
+        } else if constraint.mines == constraint.cells.len() {
+            Verdict::Mine
+        } else {
+            continue;
+        };
+        found.extend(constraint.cells.iter().map(|idx| Deduction {
+            idx: *idx,
+            verdict,
+            rule: Rule::Single,
+            reasons: vec![constraint.clone()],
+        }));
+    }
+    found
+}
+
+/// Compares the constraints sharing cells two by two: the mines of their shared
+/// cells are bounded by both, which can settle the cells only one of them has
+fn pair_deductions(constraints: &[Constraint]) -> Vec<Deduction> {
+    let mut found = Vec::new();
+    for (position, first) in constraints.iter().enumerate() {
+        for second in &constraints[position + 1..] {
+            let shared = first.cells.intersection(&second.cells).count();
+            if shared == 0 {
+                continue;
+            }
+            let first_only: Vec<usize> = first.cells.difference(&second.cells).copied().collect();
+            let second_only: Vec<usize> = second.cells.difference(&first.cells).copied().collect();
+            let least = first
+                .mines
+                .saturating_sub(first_only.len())
+                .max(second.mines.saturating_sub(second_only.len()));
+            let most = shared.min(first.mines).min(second.mines);
+            if least > most {
+                continue;
+            }
+            let rule = if first_only.is_empty() || second_only.is_empty() {
+                Rule::Subset
+            } else {
+                Rule::Overlap
+            };
+            for (only, constraint) in [(&first_only, first), (&second_only, second)] {
+                let verdict = if only.is_empty() {
+                    continue;
+                } else if constraint.mines == least {
+                    Verdict::Safe
+                } else if constraint.mines - most == only.len() {
+                    Verdict::Mine
+                } else {
+                    continue;
+                };
+                found.extend(only.iter().map(|idx| Deduction {
+                    idx: *idx,
+                    verdict,
+                    rule,
+                    reasons: vec![first.clone(), second.clone()],
+                }));
+            }
+        }
+    }
+    found
+}
+
+/// Returns every cell of `board` that is certainly safe or mined
+pub fn deduce(board: &Board) -> Vec<Deduction> {
+    Solver::new(board.clone()).solve().to_vec()
+}
+
+/// Reveals the safe cell `idx` of `grid` to `solver`, opening its region
+/// when it has no mined neighbors
+fn open(solver: &mut Solver, grid: &Grid, idx: usize) {
+    let mut to_visit = vec![idx];
+    while let Some(cell_idx) = to_visit.pop() {
+        if solver.board().numbers[cell_idx].is_some() {
+            continue;
+        }
+        let count = match grid.grid_vec[cell_idx].data {
+            CellData::MineNeighbor(count) => count,
+            CellData::Mine => unreachable!("only safe cells are opened"),
+        };
+        solver.reveal(cell_idx, count);
+        if count == 0 {
+            to_visit.extend(Grid::valid_neighbor_indices(
+                cell_idx,
+                grid.n_rows,
+                grid.n_cols,
+            ));
+        }
+    }
+}
+
+/// Returns whether every safe cell of `grid` can be revealed by deduction alone,
+/// starting from a dig on `start`
+pub fn is_solvable(grid: &Grid, start: usize) -> bool {
+    if grid.grid_vec[start].data == CellData::Mine {
+        return false;
+    }
+    let mut solver = Solver::new(Board::hidden(grid.n_rows, grid.n_cols, grid.mine_count()));
+    open(&mut solver, grid, start);
+    loop {
+        let safe: Vec<usize> = solver
+            .solve()
+            .iter()
+            .filter(|deduction| deduction.verdict == Verdict::Safe)
+            .map(|deduction| deduction.idx)
+            .collect();
+        if safe.is_empty() {
+            break;
+        }
+        for idx in safe {
+            open(&mut solver, grid, idx);
+        }
+    }
+    let hidden = solver
+        .board()
+        .numbers
+        .iter()
+        .filter(|n| n.is_none())
+        .count();
+    hidden == grid.mine_count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GameConfig;
+    use crate::state::GenerationPolicy;
+
+    #[test]
+    fn test_single_cell_rules() {
+        let grid = Grid::from_layout(
+            3,
+            3,
+            ". . .
+             . . .
+             * . .",
+        );
+        assert!(is_solvable(&grid, 2));
+    }
+
+    #[test]
+    /// Both cells of the bottom row touch the same numbers, so no deduction
+    /// can tell which of them is mined.
+    fn test_forced_guess() {
+        let grid = Grid::from_layout(
+            3,
+            2,
+            ". .
+             . .
+             * .",
+        );
+        assert!(!is_solvable(&grid, 0));
+    }
+
+    #[test]
+    /// The 1 on the left of the middle row is contained in the 2 next to it,
+    /// which tells that the third hidden cell of the 2 is mined.
+    fn test_subset_rule() {
+        let grid = Grid::from_layout(
+            3,
+            4,
+            ". . . .
+             . . . .
+             * . * .",
+        );
+        assert!(is_solvable(&grid, 0));
+    }
+
+    #[test]
+    /// The right column is walled off by mines, and only the mine count
+    /// tells that it is safe.
+    fn test_global_mine_count() {
+        let grid = Grid::from_layout(
+            2,
+            4,
+            ". . * .
+             . . * .",
+        );
+        assert!(is_solvable(&grid, 0));
+    }
+
+    #[test]
+    fn test_starting_on_a_mine() {
+        let grid = Grid::from_layout(1, 2, "* .");
+        assert!(!is_solvable(&grid, 0));
+    }
+
+    /// Known positions, drawn as rows of numbers for revealed cells and, for the
+    /// others, `s` or `m` when they should be deduced safe or mined, `.` or `*` when
+    /// they are safe or mined but cannot be deduced
+    const CORPUS: [(&str, usize, usize, &str); 7] = [
+        ("single cell", 1, 3, "1 m s"),
+        (
+            "1-1 against a wall",
+            2,
+            3,
+            "* . s
+             1 1 s",
+        ),
+        (
+            "1-2-1",
+            2,
+            3,
+            "m s m
+             1 2 1",
+        ),
+        (
+            "1-2-2-1",
+            2,
+            4,
+            "s m m s
+             1 2 2 1",
+        ),
+        (
+            "overlap",
+            2,
+            4,
+            "s m s m
+             s 1 2 1",
+        ),
+        (
+            "mine count",
+            2,
+            4,
+            "0 2 m s
+             0 2 m s",
+        ),
+        (
+            "forced guess",
+            3,
+            2,
+            "0 0
+             1 1
+             * .",
+        ),
+    ];
+
+    /// Returns the board of a position of the corpus, and the verdicts expected of it
+    fn corpus_position(
+        n_rows: usize,
+        n_cols: usize,
+        layout: &str,
+    ) -> (Board, Vec<Option<Verdict>>) {
+        let cells: Vec<&str> = layout.split_whitespace().collect();
+        assert_eq!(cells.len(), n_rows * n_cols);
+        let is_mine = |idx: usize| matches!(cells[idx], "m" | "*");
+        let numbers: Vec<Option<usize>> = cells.iter().map(|cell| cell.parse().ok()).collect();
+        for (idx, number) in numbers.iter().enumerate() {
+            if let Some(number) = number {
+                let mines = Grid::valid_neighbor_indices(idx, n_rows, n_cols)
+                    .into_iter()
+                    .filter(|nidx| is_mine(*nidx))
+                    .count();
+                assert_eq!(*number, mines, "the number of cell #{} is wrong", idx);
+            }
+        }
+        let board = Board {
+            n_rows,
+            n_cols,
+            n_mines: (0..cells.len()).filter(|idx| is_mine(*idx)).count(),
+            numbers,
+        };
+        let expected = cells
+            .iter()
+            .map(|cell| match *cell {
+                "s" => Some(Verdict::Safe),
+                "m" => Some(Verdict::Mine),
+                _ => None,
+            })
+            .collect();
+        (board, expected)
+    }
+
+    #[test]
+    fn test_corpus() {
+        for (name, n_rows, n_cols, layout) in CORPUS {
+            let (board, expected) = corpus_position(n_rows, n_cols, layout);
+            let mut verdicts = vec![None; board.numbers.len()];
+            for deduction in deduce(&board) {
+                assert_eq!(verdicts[deduction.idx], None, "{}: deduced twice", name);
+                verdicts[deduction.idx] = Some(deduction.verdict);
+            }
+            assert_eq!(verdicts, expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn test_deductions_are_justified() {
+        let (board, _) = corpus_position(1, 3, "1 m s");
+        let deductions = deduce(&board);
+        assert_eq!(
+            deductions[0],
+            Deduction {
+                idx: 1,
+                verdict: Verdict::Mine,
+                rule: Rule::Single,
+                reasons: vec![Constraint {
+                    origin: Origin::Number(0),
+                    cells: BTreeSet::from([1]),
+                    mines: 1,
+                }],
+            }
+        );
+        // once the mine is known, the mine count tells the last cell is safe
+        assert_eq!(deductions[1].idx, 2);
+        assert_eq!(deductions[1].reasons[0].origin, Origin::MineCount);
+
+        let (board, _) = corpus_position(
+            2,
+            4,
+            "s m s m
+             s 1 2 1",
+        );
+        let deductions = deduce(&board);
+        let first = deductions.iter().find(|d| d.idx == 0).unwrap();
+        assert_eq!((first.verdict, first.rule), (Verdict::Safe, Rule::Overlap));
+        let origins: Vec<Origin> = first.reasons.iter().map(|c| c.origin).collect();
+        assert_eq!(origins, [Origin::Number(5), Origin::Number(6)]);
+    }
+
+    #[test]
+    /// The board of a grid only shows its revealed numbers
+    fn test_board_from_grid() {
+        let mut grid = Grid::from_layout(1, 4, "* . . .");
+        grid.dig(3);
+        let board = Board::from_grid(&grid);
+        assert_eq!(board.numbers, [None, Some(1), Some(0), Some(0)]);
+        assert_eq!(board.n_mines, 1);
+        let deductions = deduce(&board);
+        assert_eq!(deductions.len(), 1);
+        assert_eq!(
+            (deductions[0].idx, deductions[0].verdict),
+            (0, Verdict::Mine)
+        );
+    }
+
+    #[test]
+    /// Replays the solver on boards produced by the no-guess generation policy
+    fn test_no_guess_boards_are_solvable() {
+        for config in [GameConfig::BEGINNER, GameConfig::INTERMEDIATE] {
+            for (seed, start) in [0, 8, 40, 72, 80].into_iter().enumerate() {
+                let mut grid = Grid::from_seed(config, GenerationPolicy::NoGuess, seed as u64);
+                grid.prepare_dig(start);
+                assert!(grid.grid_vec[start].data == CellData::MineNeighbor(0));
+                assert!(is_solvable(&grid, start));
+                assert!(!grid.needs_guess());
+            }
+        }
+    }
+}
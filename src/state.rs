@@ -1,12 +1,12 @@
-use std::cmp::max;
-use std::collections::HashSet;
+use std::cmp::Reverse;
+use std::collections::{BTreeSet, HashSet};
 use std::fmt::{Display, Formatter};
-use std::ops::Rem;
 
-use crate::MINE_PROPORTION;
+use crate::config::GameConfig;
+use crate::solver;
 use rand::seq::IteratorRandom;
-use rand::thread_rng;
-use yew::services::ConsoleService;
+use rand::{thread_rng, Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
 
 #[derive(Eq, PartialEq)]
 pub enum CellData {
@@ -16,91 +16,501 @@ pub enum CellData {
     MineNeighbor(usize),
 }
 
-#[derive(Eq, PartialEq, Clone, Copy)]
+/// A marker the player puts on a cell that is not revealed yet
+///
+/// Markers cycle from no marker, to `Tag`, to `Question` when question marks are
+/// enabled, and back to no marker.
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
 pub enum Flag {
-    Dig,
+    /// The player believes the cell holds a mine. Tagged cells cannot be dug.
     Tag,
+    /// The player is unsure about the cell. It can still be dug.
+    Question,
 }
 
 pub struct Cell {
-    /// A cell can be clicked on or flagged whether it has a mine or not
+    /// A cell can be clicked on or flagged whether it has a mine or not.
+    /// Revealed cells never carry a flag.
     pub is_clicked: bool,
     pub flag: Option<Flag>,
     pub data: CellData,
 }
 
+/// Result of digging a cell
+#[derive(Eq, PartialEq, Debug)]
+pub enum DigOutcome {
+    /// The cell is already revealed or tagged as a mine
+    Ignored,
+    /// The cell was safe, and its region revealed this many cells
+    Revealed(usize),
+    /// The cell held a mine
+    Exploded,
+}
+
+/// Result of chording on a cell
+#[derive(Eq, PartialEq, Debug)]
+pub enum ChordOutcome {
+    /// The cell is not a revealed number surrounded by as many flags as its number
+    Ignored,
+    /// All unflagged neighbors were revealed, with this many newly revealed cells
+    Revealed(usize),
+    /// A flag was misplaced, and the unflagged neighbor at this index held a mine
+    Exploded(usize),
+}
+
+/// Decides when the mines of a `Grid` are laid out
+#[derive(Eq, PartialEq, Clone, Copy, Debug)]
+pub enum GenerationPolicy {
+    /// Mines are placed as soon as the grid is created, so the first dig may hit one.
+    Immediate,
+    /// Mines are placed on the first dig, keeping the dug cell and its neighbors
+    /// free of mines so that the first dig always opens a region.
+    SafeFirstClick,
+    /// Like `SafeFirstClick`, but layouts are drawn again until the whole grid can be
+    /// cleared by deduction from the first dig, without ever having to guess.
+    NoGuess,
+}
+
+/// What a click on a safe cell reveals, see `Grid::three_bv`
+#[derive(Eq, PartialEq, Hash, Clone, Copy)]
+enum Reveal {
+    /// The opening numbered so, with the numbers on its edge
+    Opening(usize),
+    /// The cell at this index alone
+    Cell(usize),
+}
+
+/// How many cells `GenerationPolicy::NoGuess` lays mines on, over all the layouts it
+/// draws, before settling for one that needs a guess. That is 150 Expert layouts,
+/// which keeps the first dig to a fraction of a second on any grid.
+const NO_GUESS_BUDGET: usize = 72_000;
+
 pub struct Grid {
     /// A grid has two dimensions, and a sequence of cells
     pub n_rows: usize,
     pub n_cols: usize,
     pub grid_vec: Vec<Cell>,
+    config: GameConfig,
+    policy: GenerationPolicy,
+    mines_placed: bool,
+    /// The cell the mines were laid out around, with a deferred policy
+    first_dig: Option<usize>,
+    needs_guess: bool,
+    /// See `Grid::three_bv` and `Grid::zini`, measured once the mines are laid out
+    three_bv: usize,
+    zini: usize,
+    seed: u64,
+    rng: ChaCha8Rng,
 }
 
 impl Grid {
     /// Returns a randomly generated minesweeper grid
     ///
-    /// The number of mines equals the largest dimension. First, mined positions are
-    /// randomly chosen from the grid. Then, a loop starts from the top left cell of
-    /// the grid and fills out each `Cell`'s `data` field. If the `Cell` is not mined,
-    /// All the eight neighbors of that are mined are counted and held in `MineNeighbor(usize)`.
+    /// With `GenerationPolicy::SafeFirstClick`, every cell stays empty until
+    /// `prepare_dig` is called with the first dug cell.
     ///
     /// # Arguments
-    /// * `n_rows` - Number of rows in the grid
-    /// * `n_cols` - Number of columns in the grid
-    pub fn new(n_rows: usize, n_cols: usize, mine_prop_idx: usize) -> Self {
-        let mut grid_vec: Vec<Cell> = Vec::with_capacity((n_rows * n_cols) as usize);
-        let mine_count =
-            n_rows * n_cols / MINE_PROPORTION[mine_prop_idx.rem_euclid(MINE_PROPORTION.len())];
-        ConsoleService::log(&format!("{} mines in the grid.", mine_count));
-        let mine_indices: HashSet<usize> = HashSet::from_iter(
-            (0..(n_rows * n_cols)).choose_multiple(&mut thread_rng(), mine_count),
-        );
+    /// * `config` - Dimensions and number of mines of the grid
+    /// * `policy` - When the mines are laid out
+    pub fn new(config: GameConfig, policy: GenerationPolicy) -> Self {
+        Self::with_rng(config, policy, &mut thread_rng())
+    }
 
-        for idx in 0..(n_rows * n_cols) {
-            if mine_indices.contains(&idx) {
-                grid_vec.push(Cell {
-                    is_clicked: false,
-                    flag: None,
-                    data: CellData::Mine,
-                });
-            } else {
-                // Counting the number of mined neighbors a cell has
-                let neighbor_idx = Self::valid_neighbor_indices(idx, n_rows, n_cols);
+    /// Returns a grid whose seed is drawn from `rng`, so that the same `rng`
+    /// state always yields the same grid
+    pub fn with_rng<R: Rng + ?Sized>(
+        config: GameConfig,
+        policy: GenerationPolicy,
+        rng: &mut R,
+    ) -> Self {
+        Self::from_seed(config, policy, rng.gen())
+    }
 
-                // Count valid neighbors that are mined
-                let neighboring_mines_count = neighbor_idx
-                    .iter()
-                    .filter_map(|idx| {
-                        if mine_indices.contains(idx) {
-                            Some(true)
-                        } else {
-                            None
-                        }
-                    })
-                    .count();
+    /// Returns the grid generated by `seed`
+    ///
+    /// The same seed always yields the same mine layout. With a deferred policy,
+    /// the layout also depends on the first dug cell.
+    pub fn from_seed(config: GameConfig, policy: GenerationPolicy, seed: u64) -> Self {
+        let grid_vec = (0..config.n_cells())
+            .map(|_| Cell {
+                is_clicked: false,
+                flag: None,
+                data: CellData::MineNeighbor(0),
+            })
+            .collect();
+        let mut grid = Grid {
+            n_rows: config.n_rows(),
+            n_cols: config.n_cols(),
+            grid_vec,
+            config,
+            policy,
+            mines_placed: false,
+            first_dig: None,
+            needs_guess: false,
+            three_bv: 0,
+            zini: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        if policy == GenerationPolicy::Immediate {
+            grid.place_mines(&HashSet::new());
+            grid.measure();
+        }
+        grid
+    }
 
-                grid_vec.push(Cell {
-                    is_clicked: false,
-                    flag: None,
-                    data: CellData::MineNeighbor(neighboring_mines_count),
-                });
+    /// Returns a grid made of the given cells, with its mines already placed
+    #[cfg(test)]
+    pub fn from_cells(n_rows: usize, n_cols: usize, grid_vec: Vec<Cell>) -> Self {
+        let n_mines = grid_vec.iter().filter(|c| c.data == CellData::Mine).count();
+        Self::from_parts(
+            GameConfig::new(n_rows, n_cols, n_mines).unwrap(),
+            GenerationPolicy::Immediate,
+            0,
+            grid_vec,
+            true,
+            None,
+            false,
+        )
+    }
+
+    /// Puts a grid back together from its parts, which the caller has checked
+    /// to be consistent with each other
+    ///
+    /// The random number generator restarts from `seed`, which places the same
+    /// mines on the first dig when they have not been placed yet.
+    pub(crate) fn from_parts(
+        config: GameConfig,
+        policy: GenerationPolicy,
+        seed: u64,
+        grid_vec: Vec<Cell>,
+        mines_placed: bool,
+        first_dig: Option<usize>,
+        needs_guess: bool,
+    ) -> Self {
+        let mut grid = Grid {
+            n_rows: config.n_rows(),
+            n_cols: config.n_cols(),
+            grid_vec,
+            config,
+            policy,
+            mines_placed,
+            first_dig,
+            needs_guess,
+            three_bv: 0,
+            zini: 0,
+            seed,
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        };
+        if mines_placed {
+            grid.measure();
+        }
+        grid
+    }
+
+    /// Returns a grid drawn as rows of `*` for mines and `.` for safe cells,
+    /// separated by whitespace
+    #[cfg(test)]
+    pub fn from_layout(n_rows: usize, n_cols: usize, layout: &str) -> Self {
+        let mines: Vec<bool> = layout.split_whitespace().map(|cell| cell == "*").collect();
+        let grid_vec = (0..mines.len())
+            .map(|idx| Cell {
+                is_clicked: false,
+                flag: None,
+                data: if mines[idx] {
+                    CellData::Mine
+                } else {
+                    CellData::MineNeighbor(
+                        Self::valid_neighbor_indices(idx, n_rows, n_cols)
+                            .into_iter()
+                            .filter(|nidx| mines[*nidx])
+                            .count(),
+                    )
+                },
+            })
+            .collect();
+        Self::from_cells(n_rows, n_cols, grid_vec)
+    }
+
+    pub fn config(&self) -> GameConfig {
+        self.config
+    }
+
+    pub fn policy(&self) -> GenerationPolicy {
+        self.policy
+    }
+
+    /// Returns the seed the grid was generated from
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn mines_placed(&self) -> bool {
+        self.mines_placed
+    }
+
+    /// Returns the cell the mines were laid out around, which together with the
+    /// seed makes the layout of a deferred policy, see `crate::replay`
+    pub fn first_dig(&self) -> Option<usize> {
+        self.first_dig
+    }
+
+    /// Returns whether `GenerationPolicy::NoGuess` gave up looking for a layout the
+    /// solver clears, and kept one that may need a guess
+    pub fn needs_guess(&self) -> bool {
+        self.needs_guess
+    }
+
+    /// Places the mines before `idx` is dug, if they have not been placed yet
+    ///
+    /// The dug cell and its neighbors are kept free of mines. When the grid is too
+    /// crowded to spare the whole neighborhood, only the dug cell is kept free.
+    /// With `GenerationPolicy::NoGuess`, layouts the solver cannot clear from `idx`
+    /// are rejected, for as many layouts as `NO_GUESS_BUDGET` allows on the grid,
+    /// after which `needs_guess` tells that the last one was kept anyway.
+    pub fn prepare_dig(&mut self, idx: usize) {
+        if self.mines_placed {
+            return;
+        }
+        self.first_dig = Some(idx);
+        let mut safe_indices: HashSet<usize> =
+            HashSet::from_iter(Self::valid_neighbor_indices(idx, self.n_rows, self.n_cols));
+        safe_indices.insert(idx);
+        if self.grid_vec.len() - safe_indices.len() < self.config.n_mines() {
+            safe_indices = HashSet::from([idx]);
+        }
+        self.place_mines(&safe_indices);
+        if self.policy == GenerationPolicy::NoGuess {
+            let attempts = (NO_GUESS_BUDGET / self.grid_vec.len()).max(1);
+            self.needs_guess = true;
+            for attempt in 1..=attempts {
+                if solver::is_solvable(self, idx) {
+                    self.needs_guess = false;
+                    break;
+                }
+                if attempt < attempts {
+                    self.place_mines(&safe_indices);
+                }
             }
         }
+        self.measure();
+    }
 
-        Grid {
-            n_rows,
-            n_cols,
-            grid_vec,
+    /// Randomly chooses mined positions outside of `excluded`, drawing from the grid's
+    /// seeded random number generator. Then, a loop starts from
+    /// the top left cell of the grid and fills out each `Cell`'s `data` field. If the
+    /// `Cell` is not mined, all the eight neighbors of that are mined are counted and
+    /// held in `MineNeighbor(usize)`.
+    fn place_mines(&mut self, excluded: &HashSet<usize>) {
+        let mine_indices: HashSet<usize> = HashSet::from_iter(
+            (0..self.grid_vec.len())
+                .filter(|idx| !excluded.contains(idx))
+                .choose_multiple(&mut self.rng, self.config.n_mines()),
+        );
+
+        for idx in 0..self.grid_vec.len() {
+            self.grid_vec[idx].data = if mine_indices.contains(&idx) {
+                CellData::Mine
+            } else {
+                // Count valid neighbors that are mined
+                let neighboring_mines_count =
+                    Self::valid_neighbor_indices(idx, self.n_rows, self.n_cols)
+                        .iter()
+                        .filter(|nidx| mine_indices.contains(nidx))
+                        .count();
+                CellData::MineNeighbor(neighboring_mines_count)
+            };
         }
+        self.mines_placed = true;
     }
 
+    /// Measures the board once its layout is final, which the rejected layouts
+    /// of `GenerationPolicy::NoGuess` never are
+    fn measure(&mut self) {
+        self.three_bv = self.count_three_bv();
+        self.zini = self.count_zini();
+    }
+
+    /// Returns the number of mines in the grid, including the ones yet to be placed
     pub fn mine_count(&self) -> usize {
+        self.config.n_mines()
+    }
+
+    /// Returns the number of cells tagged as mines
+    pub fn flag_count(&self) -> usize {
         self.grid_vec
             .iter()
-            .filter(|c| c.data == CellData::Mine)
+            .filter(|c| c.flag == Some(Flag::Tag))
             .count()
     }
 
+    /// Returns the number of mines minus the number of flags, which goes
+    /// negative when more flags than mines have been placed
+    pub fn mines_remaining(&self) -> isize {
+        self.mine_count() as isize - self.flag_count() as isize
+    }
+
+    /// Returns the number of safe cells that are not revealed yet
+    pub fn safe_cells_left(&self) -> usize {
+        let revealed_safe_cells = self
+            .grid_vec
+            .iter()
+            .filter(|c| c.is_clicked && c.data != CellData::Mine)
+            .count();
+        self.grid_vec.len() - self.mine_count() - revealed_safe_cells
+    }
+
+    /// Returns whether every safe cell is revealed
+    pub fn is_cleared(&self) -> bool {
+        self.mines_placed && self.safe_cells_left() == 0
+    }
+
+    /// Returns whether a mine has been revealed
+    pub fn is_exploded(&self) -> bool {
+        self.grid_vec
+            .iter()
+            .any(|c| c.is_clicked && c.data == CellData::Mine)
+    }
+
+    /// Returns the 3BV of the grid, the least number of clicks that reveals every safe
+    /// cell without chording: one per opening, the region revealed by a cell without
+    /// mined neighbors, plus one per number not on the edge of an opening
+    ///
+    /// Zero until the mines are placed.
+    pub fn three_bv(&self) -> usize {
+        self.three_bv
+    }
+
+    /// Returns the ZiNi of the grid, about the least number of clicks that reveals
+    /// every safe cell when flags and chords are allowed, which is at most the 3BV
+    ///
+    /// It is found greedily: as long as revealing a number, flagging the mines
+    /// around it and chording it takes fewer clicks than revealing its neighbors
+    /// one by one, the number sparing the most clicks is chorded. The safe cells
+    /// left are then revealed one by one, as counted by 3BV.
+    ///
+    /// Zero until the mines are placed.
+    pub fn zini(&self) -> usize {
+        self.zini
+    }
+
+    /// Returns what a click on each safe cell reveals, counted once by 3BV: its
+    /// opening, when it has one, or the cell alone. Mines get `None`.
+    fn reveals(&self) -> Vec<Option<Reveal>> {
+        let mut reveals = vec![None; self.grid_vec.len()];
+        let mut openings = 0;
+        for idx in 0..self.grid_vec.len() {
+            if reveals[idx].is_some() || self.grid_vec[idx].data != CellData::MineNeighbor(0) {
+                continue;
+            }
+            // a number between two openings is revealed by either, it goes with the first
+            for cell_idx in self.flood_fill(idx, |nidx| reveals[nidx].is_none()) {
+                reveals[cell_idx] = Some(Reveal::Opening(openings));
+            }
+            openings += 1;
+        }
+        for (idx, reveal) in reveals.iter_mut().enumerate() {
+            if reveal.is_none() && self.grid_vec[idx].data != CellData::Mine {
+                *reveal = Some(Reveal::Cell(idx));
+            }
+        }
+        reveals
+    }
+
+    fn count_three_bv(&self) -> usize {
+        self.reveals()
+            .iter()
+            .flatten()
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    fn count_zini(&self) -> usize {
+        let reveals = self.reveals();
+        let mut revealed = vec![false; self.grid_vec.len()];
+        let mut flagged = vec![false; self.grid_vec.len()];
+        // clicks revealing the hidden safe cells among `cells` one by one
+        let clicks_to_reveal = |cells: &[usize], revealed: &[bool]| {
+            cells
+                .iter()
+                .filter(|idx| !revealed[**idx])
+                .filter_map(|idx| reveals[*idx])
+                .collect::<HashSet<_>>()
+                .len()
+        };
+        // a cell and its neighbors, which a chord on the cell reveals or needs flagged
+        let around = |idx: usize| {
+            let mut cells = Self::valid_neighbor_indices(idx, self.n_rows, self.n_cols);
+            cells.push(idx);
+            cells
+        };
+        // the clicks a chord on `idx` spares over revealing the cells around it one
+        // by one, and the clicks it takes: revealing `idx`, flagging its mines, chording
+        let chord = |idx: usize, revealed: &[bool], flagged: &[bool]| {
+            let cells = around(idx);
+            let flags = cells
+                .iter()
+                .filter(|nidx| self.grid_vec[**nidx].data == CellData::Mine && !flagged[**nidx])
+                .count();
+            let cost = usize::from(!revealed[idx]) + flags + 1;
+            (
+                clicks_to_reveal(&cells, revealed) as isize - cost as isize,
+                cost,
+            )
+        };
+        let is_number = |idx: usize| {
+            !matches!(
+                self.grid_vec[idx].data,
+                CellData::Mine | CellData::MineNeighbor(0)
+            )
+        };
+
+        // the premium of every number, and the numbers by premium, highest first,
+        // then by index
+        let mut premiums = vec![0; self.grid_vec.len()];
+        let mut by_premium = BTreeSet::new();
+        for idx in (0..self.grid_vec.len()).filter(|idx| is_number(*idx)) {
+            premiums[idx] = chord(idx, &revealed, &flagged).0;
+            by_premium.insert((Reverse(premiums[idx]), idx));
+        }
+        let mut clicks = 0;
+        while let Some(&(Reverse(premium), best)) = by_premium.first() {
+            if premium <= 0 {
+                break;
+            }
+            clicks += chord(best, &revealed, &flagged).1;
+            let mut changed = Vec::new();
+            for idx in around(best) {
+                if self.grid_vec[idx].data == CellData::Mine {
+                    if !flagged[idx] {
+                        flagged[idx] = true;
+                        changed.push(idx);
+                    }
+                } else if !revealed[idx] {
+                    for cell_idx in self.flood_fill(idx, |nidx| !revealed[nidx]) {
+                        revealed[cell_idx] = true;
+                        changed.push(cell_idx);
+                    }
+                }
+            }
+            // only the numbers around the cells just revealed or flagged change premium
+            let mut to_update: Vec<usize> = changed
+                .into_iter()
+                .flat_map(around)
+                .filter(|idx| is_number(*idx))
+                .collect();
+            to_update.sort_unstable();
+            to_update.dedup();
+            for idx in to_update {
+                by_premium.remove(&(Reverse(premiums[idx]), idx));
+                premiums[idx] = chord(idx, &revealed, &flagged).0;
+                by_premium.insert((Reverse(premiums[idx]), idx));
+            }
+        }
+        let all_cells: Vec<usize> = (0..self.grid_vec.len()).collect();
+        clicks + clicks_to_reveal(&all_cells, &revealed)
+    }
+
     /// Return indices of all possible neighbors of a cell in a grid
     /// ToDo: Add tests
     pub fn valid_neighbor_indices(idx: usize, n_rows: usize, n_cols: usize) -> Vec<usize> {
@@ -143,25 +553,111 @@ impl Grid {
         neighbor_idx
     }
 
-    pub fn reveal_empty_cells(&mut self, idx: usize) -> usize {
-        let mut to_visit: Vec<usize> = Vec::new();
-        let mut visited: HashSet<usize> = HashSet::new();
-        to_visit.push(idx);
-
-        while !to_visit.is_empty() {
-            let cell_idx = to_visit.pop().unwrap();
-            visited.insert(cell_idx);
-            self.grid_vec[cell_idx].is_clicked = true;
+    /// Returns `idx` and the cells reached from it by spreading from every reached
+    /// cell without mined neighbors to the neighbors `can_enter` lets in
+    fn flood_fill(&self, idx: usize, can_enter: impl Fn(usize) -> bool) -> Vec<usize> {
+        let mut to_visit: Vec<usize> = vec![idx];
+        let mut visited: HashSet<usize> = HashSet::from([idx]);
+        let mut region = Vec::new();
+
+        while let Some(cell_idx) = to_visit.pop() {
+            region.push(cell_idx);
             if self.grid_vec[cell_idx].data == CellData::MineNeighbor(0) {
-                let mut neighbor_indices =
-                    Grid::valid_neighbor_indices(cell_idx, self.n_rows, self.n_cols)
-                        .into_iter()
-                        .filter(|nidx| !visited.contains(nidx) && !self.grid_vec[*nidx].is_clicked)
-                        .collect();
-                to_visit.append(&mut neighbor_indices);
+                for nidx in Grid::valid_neighbor_indices(cell_idx, self.n_rows, self.n_cols) {
+                    if can_enter(nidx) && visited.insert(nidx) {
+                        to_visit.push(nidx);
+                    }
+                }
+            }
+        }
+        region
+    }
+
+    /// Reveals a cell and, when it has no mined neighbors, the region around it.
+    /// Cells tagged as mines are left untouched, and question marks are cleared.
+    /// Returns the number of cells revealed.
+    pub fn reveal_empty_cells(&mut self, idx: usize) -> usize {
+        let region = self.flood_fill(idx, |nidx| {
+            !self.grid_vec[nidx].is_clicked && self.grid_vec[nidx].flag != Some(Flag::Tag)
+        });
+        for cell_idx in &region {
+            self.grid_vec[*cell_idx].is_clicked = true;
+            self.grid_vec[*cell_idx].flag = None;
+        }
+        region.len()
+    }
+
+    /// Reveals a cell, placing the mines first if it is the first dig of the grid
+    ///
+    /// Cells tagged as mines are not dug, so the tag has to be removed first.
+    pub fn dig(&mut self, idx: usize) -> DigOutcome {
+        let cell = &self.grid_vec[idx];
+        if cell.is_clicked || cell.flag == Some(Flag::Tag) {
+            return DigOutcome::Ignored;
+        }
+        self.prepare_dig(idx);
+        if self.grid_vec[idx].data == CellData::Mine {
+            self.grid_vec[idx].is_clicked = true;
+            self.grid_vec[idx].flag = None;
+            return DigOutcome::Exploded;
+        }
+        DigOutcome::Revealed(self.reveal_empty_cells(idx))
+    }
+
+    /// Moves the marker of a cell that is not revealed yet to the next one in the cycle,
+    /// skipping question marks unless `allow_question` is set. Returns whether the cell changed.
+    pub fn cycle_flag(&mut self, idx: usize, allow_question: bool) -> bool {
+        let cell = &mut self.grid_vec[idx];
+        if cell.is_clicked {
+            return false;
+        }
+        cell.flag = match cell.flag {
+            None => Some(Flag::Tag),
+            Some(Flag::Tag) if allow_question => Some(Flag::Question),
+            Some(Flag::Tag) | Some(Flag::Question) => None,
+        };
+        true
+    }
+
+    /// Reveals every unflagged neighbor of a revealed number at once, provided that
+    /// exactly as many of its neighbors are flagged as its number says
+    ///
+    /// Neighbors without mined neighbors of their own open their region as in
+    /// `reveal_empty_cells`. If a flag was misplaced, the mine left unflagged is
+    /// revealed and reported.
+    pub fn chord(&mut self, idx: usize) -> ChordOutcome {
+        let count = match self.grid_vec[idx].data {
+            CellData::MineNeighbor(count) if self.grid_vec[idx].is_clicked && count > 0 => count,
+            _ => return ChordOutcome::Ignored,
+        };
+        let neighbor_indices = Self::valid_neighbor_indices(idx, self.n_rows, self.n_cols);
+        let flag_count = neighbor_indices
+            .iter()
+            .filter(|nidx| self.grid_vec[**nidx].flag == Some(Flag::Tag))
+            .count();
+        if flag_count != count {
+            return ChordOutcome::Ignored;
+        }
+
+        let mut exploded = None;
+        let mut revealed = 0;
+        for nidx in neighbor_indices {
+            let neighbor = &mut self.grid_vec[nidx];
+            if neighbor.is_clicked || neighbor.flag == Some(Flag::Tag) {
+                continue;
+            }
+            if neighbor.data == CellData::Mine {
+                neighbor.is_clicked = true;
+                neighbor.flag = None;
+                exploded.get_or_insert(nidx);
+            } else {
+                revealed += self.reveal_empty_cells(nidx);
             }
         }
-        visited.len()
+        match exploded {
+            Some(mine_idx) => ChordOutcome::Exploded(mine_idx),
+            None => ChordOutcome::Revealed(revealed),
+        }
     }
 
     /// convert 1D index to a 2D index
@@ -182,6 +678,7 @@ impl Grid {
 
     /// Returns the grid with clicked cells revealed
     /// used for testing the `reveal_empty_cells`
+    #[cfg(test)]
     pub fn overlay_display(&self) -> String {
         let mut grid_string = String::new();
         for i in 0..self.n_rows {
@@ -240,11 +737,6 @@ mod tests {
 
     #[test]
     fn test_idx_to_xy() {
-        let grid = Grid {
-            n_rows: 3,
-            n_cols: 4,
-            grid_vec: Vec::new(),
-        };
         assert_eq!(Grid::idx_to_xy(1, 3, 4), Some((0, 1)));
         assert_eq!(Grid::idx_to_xy(4, 3, 4), Some((1, 0)));
         assert_eq!(Grid::idx_to_xy(5, 3, 4), Some((1, 1)));
@@ -253,11 +745,6 @@ mod tests {
 
     #[test]
     fn test_xy_to_idx() {
-        let grid = Grid {
-            n_rows: 3,
-            n_cols: 4,
-            grid_vec: Vec::new(),
-        };
         assert_eq!(Grid::xy_to_idx((0, 0), 3, 4), Some(0));
         assert_eq!(Grid::xy_to_idx((0, 1), 3, 4), Some(1));
         assert_eq!(Grid::xy_to_idx((1, 0), 3, 4), Some(4));
@@ -267,23 +754,28 @@ mod tests {
 
     #[test]
     fn test_overlay_display() {
-        let grid = Grid::new(3, 3, 0);
+        let grid = Grid::new(
+            GameConfig::new(3, 3, 0).unwrap(),
+            GenerationPolicy::Immediate,
+        );
         println!("{}", grid.overlay_display());
         assert_eq!(grid.overlay_display(), "? ? ? \n? ? ? \n? ? ? \n");
     }
 
     #[test]
+    /// ```text
     /// * 1 0
     /// 1 1 0
     /// 0 0 0
+    /// ```
     /// Given the grid above, if the bottom right cell is clicked,
     /// all cells except the top left one should be revealed, or clicked.
     fn test_reveal_empty_cells() {
         let idx = 8;
-        let mut grid = Grid {
-            n_rows: 3,
-            n_cols: 3,
-            grid_vec: vec![
+        let mut grid = Grid::from_cells(
+            3,
+            3,
+            vec![
                 Cell {
                     is_clicked: false,
                     flag: None,
@@ -330,15 +822,13 @@ mod tests {
                     data: CellData::MineNeighbor(0),
                 },
             ],
-        };
+        );
         grid.grid_vec[idx].is_clicked = true;
         grid.reveal_empty_cells(idx);
         println!("{}", grid.overlay_display());
     }
 
     #[test]
-    ///
-    ///
     fn test_valid_neighbor_indices() {
         assert_eq!(Grid::valid_neighbor_indices(0, 3, 3).len(), 3);
         assert_eq!(Grid::valid_neighbor_indices(1, 3, 3).len(), 5);
@@ -350,4 +840,431 @@ mod tests {
         assert_eq!(Grid::valid_neighbor_indices(7, 3, 3).len(), 5);
         assert_eq!(Grid::valid_neighbor_indices(8, 3, 3).len(), 3);
     }
+
+    #[test]
+    fn test_immediate_policy_places_mines_on_creation() {
+        let grid = Grid::new(
+            GameConfig::new(10, 10, 20).unwrap(),
+            GenerationPolicy::Immediate,
+        );
+        assert!(grid.mines_placed());
+        let placed = grid
+            .grid_vec
+            .iter()
+            .filter(|c| c.data == CellData::Mine)
+            .count();
+        assert_eq!(placed, grid.mine_count());
+    }
+
+    #[test]
+    fn test_safe_first_click_defers_mines() {
+        let grid = Grid::new(
+            GameConfig::new(10, 10, 33).unwrap(),
+            GenerationPolicy::SafeFirstClick,
+        );
+        assert!(!grid.mines_placed());
+        assert_eq!(grid.mine_count(), 33);
+        assert!(grid.grid_vec.iter().all(|c| c.data != CellData::Mine));
+    }
+
+    #[test]
+    fn test_grid_follows_config() {
+        let grid = Grid::new(GameConfig::EXPERT, GenerationPolicy::Immediate);
+        assert_eq!(grid.n_rows, 16);
+        assert_eq!(grid.n_cols, 30);
+        assert_eq!(grid.grid_vec.len(), 480);
+        let placed = grid
+            .grid_vec
+            .iter()
+            .filter(|c| c.data == CellData::Mine)
+            .count();
+        assert_eq!(placed, 99);
+        assert_eq!(grid.config(), GameConfig::EXPERT);
+    }
+
+    #[test]
+    /// Whatever cell is dug first, and whatever the mine count, the dug cell
+    /// must have no mined neighbors so that it opens a region.
+    fn test_safe_first_click_opens_a_region() {
+        for n_mines in [10, 20, 33, 91] {
+            for idx in 0..100 {
+                let mut grid = Grid::new(
+                    GameConfig::new(10, 10, n_mines).unwrap(),
+                    GenerationPolicy::SafeFirstClick,
+                );
+                grid.prepare_dig(idx);
+                assert!(grid.mines_placed());
+                assert!(grid.grid_vec[idx].data == CellData::MineNeighbor(0));
+                let placed = grid
+                    .grid_vec
+                    .iter()
+                    .filter(|c| c.data == CellData::Mine)
+                    .count();
+                assert_eq!(placed, grid.mine_count());
+
+                let revealed = grid.reveal_empty_cells(idx);
+                let neighbor_count = Grid::valid_neighbor_indices(idx, 10, 10).len();
+                assert!(revealed > neighbor_count);
+            }
+        }
+    }
+
+    #[test]
+    fn test_prepare_dig_keeps_placed_mines() {
+        let mut grid = Grid::new(
+            GameConfig::new(10, 10, 33).unwrap(),
+            GenerationPolicy::SafeFirstClick,
+        );
+        grid.prepare_dig(0);
+        let layout = grid.to_string();
+        grid.prepare_dig(99);
+        assert_eq!(grid.to_string(), layout);
+    }
+
+    #[test]
+    /// A 3x3 grid with 3 mines cannot spare the whole neighborhood of the
+    /// center cell, so only the dug cell itself is kept free of mines.
+    fn test_safe_first_click_on_crowded_grid() {
+        let mut grid = Grid::new(
+            GameConfig::new(3, 3, 3).unwrap(),
+            GenerationPolicy::SafeFirstClick,
+        );
+        grid.prepare_dig(4);
+        assert!(grid.grid_vec[4].data == CellData::MineNeighbor(3));
+    }
+
+    #[test]
+    /// A grid this crowded hardly ever has a layout the solver clears, so the
+    /// search gives up within its budget and tells that a guess may be needed
+    fn test_no_guess_gives_up_on_crowded_grids() {
+        let config = GameConfig::new(16, 30, 200).unwrap();
+        let mut grid = Grid::from_seed(config, GenerationPolicy::NoGuess, 3);
+        grid.prepare_dig(240);
+        assert!(grid.needs_guess());
+        assert!(!solver::is_solvable(&grid, 240));
+        assert_eq!(
+            grid.grid_vec
+                .iter()
+                .filter(|c| c.data == CellData::Mine)
+                .count(),
+            200
+        );
+        assert!(grid.grid_vec[240].data != CellData::Mine);
+    }
+
+    #[test]
+    fn test_same_seed_same_layout() {
+        for policy in [
+            GenerationPolicy::Immediate,
+            GenerationPolicy::SafeFirstClick,
+            GenerationPolicy::NoGuess,
+        ] {
+            let mut first = Grid::from_seed(GameConfig::INTERMEDIATE, policy, 42);
+            let mut second = Grid::from_seed(GameConfig::INTERMEDIATE, policy, 42);
+            first.prepare_dig(55);
+            second.prepare_dig(55);
+            assert_eq!(first.seed(), 42);
+            assert_eq!(first.to_string(), second.to_string());
+        }
+    }
+
+    #[test]
+    fn test_different_seeds_different_layouts() {
+        let first = Grid::from_seed(GameConfig::BEGINNER, GenerationPolicy::Immediate, 1);
+        let second = Grid::from_seed(GameConfig::BEGINNER, GenerationPolicy::Immediate, 2);
+        assert_ne!(first.to_string(), second.to_string());
+    }
+
+    #[test]
+    fn test_with_rng_is_deterministic() {
+        let first = Grid::with_rng(
+            GameConfig::EXPERT,
+            GenerationPolicy::Immediate,
+            &mut ChaCha8Rng::seed_from_u64(7),
+        );
+        let second = Grid::with_rng(
+            GameConfig::EXPERT,
+            GenerationPolicy::Immediate,
+            &mut ChaCha8Rng::seed_from_u64(7),
+        );
+        assert_eq!(first.seed(), second.seed());
+        assert_eq!(first.to_string(), second.to_string());
+
+        let replayed = Grid::from_seed(
+            GameConfig::EXPERT,
+            GenerationPolicy::Immediate,
+            first.seed(),
+        );
+        assert_eq!(first.to_string(), replayed.to_string());
+    }
+
+    /// ```text
+    /// * 1 0 0
+    /// 1 1 0 0
+    /// 0 0 1 1
+    /// 0 0 1 *
+    /// ```
+    fn chord_grid() -> Grid {
+        Grid::from_layout(
+            4,
+            4,
+            "* . . .
+             . . . .
+             . . . .
+             . . . *",
+        )
+    }
+
+    #[test]
+    fn test_chord_reveals_unflagged_neighbors() {
+        let mut grid = chord_grid();
+        grid.grid_vec[5].is_clicked = true;
+        grid.grid_vec[0].flag = Some(Flag::Tag);
+        assert_eq!(grid.chord(5), ChordOutcome::Revealed(13));
+        assert_eq!(
+            grid.overlay_display(),
+            "? 1 0 0 \n1 1 0 0 \n0 0 1 1 \n0 0 1 ? \n"
+        );
+    }
+
+    #[test]
+    fn test_chord_cascades_through_empty_cells() {
+        let mut grid = chord_grid();
+        grid.grid_vec[14].is_clicked = true;
+        grid.grid_vec[15].flag = Some(Flag::Tag);
+        assert_eq!(grid.chord(14), ChordOutcome::Revealed(13));
+        assert!(!grid.grid_vec[0].is_clicked);
+        assert!(!grid.grid_vec[15].is_clicked);
+    }
+
+    #[test]
+    fn test_chord_with_misplaced_flag_explodes() {
+        let mut grid = chord_grid();
+        grid.grid_vec[5].is_clicked = true;
+        grid.grid_vec[1].flag = Some(Flag::Tag);
+        assert_eq!(grid.chord(5), ChordOutcome::Exploded(0));
+        assert!(grid.grid_vec[0].is_clicked);
+    }
+
+    #[test]
+    fn test_chord_is_ignored() {
+        let mut grid = chord_grid();
+        // not revealed yet
+        grid.grid_vec[0].flag = Some(Flag::Tag);
+        assert_eq!(grid.chord(5), ChordOutcome::Ignored);
+        // not enough flags around
+        grid.grid_vec[0].flag = None;
+        grid.grid_vec[5].is_clicked = true;
+        assert_eq!(grid.chord(5), ChordOutcome::Ignored);
+        // too many flags around
+        grid.grid_vec[0].flag = Some(Flag::Tag);
+        grid.grid_vec[1].flag = Some(Flag::Tag);
+        assert_eq!(grid.chord(5), ChordOutcome::Ignored);
+        // revealed cells without mined neighbors have nothing to chord
+        grid.grid_vec[3].is_clicked = true;
+        assert_eq!(grid.chord(3), ChordOutcome::Ignored);
+        assert!(grid.grid_vec.iter().filter(|c| c.is_clicked).count() == 2);
+    }
+
+    #[test]
+    fn test_flag_cycle_with_question_marks() {
+        let mut grid = chord_grid();
+        assert!(grid.grid_vec[0].flag.is_none());
+        assert!(grid.cycle_flag(0, true));
+        assert_eq!(grid.grid_vec[0].flag, Some(Flag::Tag));
+        assert!(grid.cycle_flag(0, true));
+        assert_eq!(grid.grid_vec[0].flag, Some(Flag::Question));
+        assert!(grid.cycle_flag(0, true));
+        assert_eq!(grid.grid_vec[0].flag, None);
+    }
+
+    #[test]
+    fn test_flag_cycle_without_question_marks() {
+        let mut grid = chord_grid();
+        assert!(grid.cycle_flag(0, false));
+        assert_eq!(grid.grid_vec[0].flag, Some(Flag::Tag));
+        assert!(grid.cycle_flag(0, false));
+        assert_eq!(grid.grid_vec[0].flag, None);
+
+        // a question mark left from when they were enabled is cleared
+        grid.grid_vec[0].flag = Some(Flag::Question);
+        assert!(grid.cycle_flag(0, false));
+        assert_eq!(grid.grid_vec[0].flag, None);
+    }
+
+    #[test]
+    fn test_no_flag_on_revealed_cells() {
+        let mut grid = chord_grid();
+        grid.grid_vec[5].is_clicked = true;
+        assert!(!grid.cycle_flag(5, true));
+        assert!(!grid.cycle_flag(5, false));
+        assert_eq!(grid.grid_vec[5].flag, None);
+    }
+
+    #[test]
+    fn test_dig_tagged_cell_is_ignored() {
+        let mut grid = chord_grid();
+        grid.cycle_flag(0, true);
+        assert_eq!(grid.dig(0), DigOutcome::Ignored);
+        assert!(!grid.grid_vec[0].is_clicked);
+        grid.cycle_flag(3, true);
+        assert_eq!(grid.dig(3), DigOutcome::Ignored);
+        assert!(!grid.grid_vec[3].is_clicked);
+    }
+
+    #[test]
+    fn test_dig_question_marked_cell() {
+        let mut grid = chord_grid();
+        grid.grid_vec[5].flag = Some(Flag::Question);
+        assert_eq!(grid.dig(5), DigOutcome::Revealed(1));
+        assert!(grid.grid_vec[5].is_clicked);
+        assert_eq!(grid.grid_vec[5].flag, None);
+        assert_eq!(grid.dig(5), DigOutcome::Ignored);
+
+        grid.grid_vec[0].flag = Some(Flag::Question);
+        assert_eq!(grid.dig(0), DigOutcome::Exploded);
+        assert_eq!(grid.grid_vec[0].flag, None);
+    }
+
+    #[test]
+    /// A cascade stops at tagged cells, even safe ones, and clears question marks
+    fn test_cascade_keeps_tagged_cells() {
+        let mut grid = chord_grid();
+        grid.grid_vec[3].flag = Some(Flag::Tag);
+        grid.grid_vec[8].flag = Some(Flag::Question);
+        assert_eq!(grid.dig(12), DigOutcome::Revealed(13));
+        assert!(!grid.grid_vec[3].is_clicked);
+        assert_eq!(grid.grid_vec[3].flag, Some(Flag::Tag));
+        assert!(grid.grid_vec[8].is_clicked);
+        assert_eq!(grid.grid_vec[8].flag, None);
+    }
+
+    #[test]
+    fn test_safe_cells_left() {
+        let mut grid = Grid::new(GameConfig::BEGINNER, GenerationPolicy::SafeFirstClick);
+        assert_eq!(grid.safe_cells_left(), 71);
+        assert!(!grid.is_cleared());
+        let revealed = match grid.dig(40) {
+            DigOutcome::Revealed(count) => count,
+            outcome => panic!("unexpected {:?}", outcome),
+        };
+        assert_eq!(grid.safe_cells_left(), 71 - revealed);
+        assert!(!grid.is_exploded());
+    }
+
+    #[test]
+    fn test_cleared_and_exploded() {
+        let mut grid = chord_grid();
+        assert_eq!(grid.dig(3), DigOutcome::Revealed(14));
+        assert!(grid.is_cleared());
+        assert!(!grid.is_exploded());
+        assert_eq!(grid.dig(0), DigOutcome::Exploded);
+        assert!(grid.is_exploded());
+    }
+
+    #[test]
+    fn test_mines_remaining() {
+        let mut grid = chord_grid();
+        assert_eq!(grid.mines_remaining(), 2);
+        grid.cycle_flag(0, true);
+        assert_eq!(grid.flag_count(), 1);
+        assert_eq!(grid.mines_remaining(), 1);
+        // question marks do not count as flags
+        grid.cycle_flag(0, true);
+        assert_eq!(grid.mines_remaining(), 2);
+        for idx in [1, 2, 3] {
+            grid.cycle_flag(idx, true);
+        }
+        assert_eq!(grid.mines_remaining(), -1);
+    }
+
+    #[test]
+    fn test_three_bv() {
+        // a single opening reaches every safe cell
+        assert_eq!(chord_grid().three_bv(), 1);
+        // no opening, every number takes a click
+        assert_eq!(
+            Grid::from_layout(
+                2,
+                3,
+                "* . *
+                 . . .",
+            )
+            .three_bv(),
+            4
+        );
+        // an opening, and a number walled off by mines
+        assert_eq!(Grid::from_layout(1, 5, ". . * . *").three_bv(), 2);
+        // two openings split by a column of mines
+        assert_eq!(
+            Grid::from_layout(
+                3,
+                5,
+                ". . * . .
+                 . . * . .
+                 . . * . .",
+            )
+            .three_bv(),
+            2
+        );
+        // the 2 in the middle is revealed by either of the two corner openings
+        assert_eq!(
+            Grid::from_layout(
+                3,
+                3,
+                "* . .
+                 . . .
+                 . . *",
+            )
+            .three_bv(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_zini() {
+        // nothing beats the single click on the opening
+        assert_eq!(chord_grid().zini(), 1);
+        // chording the bottom 2 takes 4 clicks, as many as clicking every number
+        let grid = Grid::from_layout(
+            2,
+            3,
+            "* . *
+             . . .",
+        );
+        assert_eq!((grid.three_bv(), grid.zini()), (4, 4));
+        // flagging the mine, revealing and chording the top 1 reveals five cells in
+        // three clicks, then chording the 1 on the left reveals two in one click,
+        // and the last corner takes a click of its own
+        let grid = Grid::from_layout(
+            3,
+            3,
+            ". . .
+             . * .
+             . . .",
+        );
+        assert_eq!((grid.three_bv(), grid.zini()), (8, 5));
+    }
+
+    #[test]
+    fn test_zini_is_at_most_the_three_bv() {
+        let largest = GameConfig::new(GameConfig::MAX_ROWS, GameConfig::MAX_COLS, 1500).unwrap();
+        for (config, seed) in (0..20)
+            .map(|seed| (GameConfig::EXPERT, seed))
+            .chain([(largest, 0)])
+        {
+            let grid = Grid::from_seed(config, GenerationPolicy::Immediate, seed);
+            assert!(grid.zini() > 0);
+            assert!(grid.zini() <= grid.three_bv());
+        }
+    }
+
+    #[test]
+    fn test_three_bv_counted_when_mines_are_placed() {
+        let mut grid = Grid::from_seed(GameConfig::BEGINNER, GenerationPolicy::SafeFirstClick, 7);
+        assert_eq!((grid.three_bv(), grid.zini()), (0, 0));
+        grid.prepare_dig(40);
+        assert!(grid.three_bv() > 0);
+        assert_eq!(grid.three_bv(), grid.count_three_bv());
+    }
 }
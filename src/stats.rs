@@ -0,0 +1,309 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::GameConfig;
+use crate::game::{efficiency, three_bv_per_second, GameResult};
+use crate::replay::ReplayCode;
+use crate::save::{read_envelope, write_envelope, LoadError};
+
+/// Version written by `Statistics::to_json`
+pub const STATS_VERSION: u32 = 4;
+
+/// A finished game, as kept in the statistics
+#[derive(Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Debug)]
+pub struct GameRecord {
+    pub n_rows: usize,
+    pub n_cols: usize,
+    pub n_mines: usize,
+    /// Code of the board, which versions 1 to 3 did not keep: their seed alone
+    /// could not lay the board out again
+    #[serde(default)]
+    pub replay: Option<ReplayCode>,
+    pub won: bool,
+    pub elapsed_ms: u64,
+    /// Clicks of every kind, see `crate::game::Clicks`
+    pub clicks: usize,
+    /// Hints used, which version 1 did not count
+    #[serde(default)]
+    pub hints: usize,
+    /// 3BV of the board, which versions 1 and 2 did not keep
+    #[serde(default)]
+    pub three_bv: Option<usize>,
+}
+
+impl GameRecord {
+    /// Returns whether the record was played with `config`
+    pub fn is_played_with(&self, config: GameConfig) -> bool {
+        (self.n_rows, self.n_cols, self.n_mines)
+            == (config.n_rows(), config.n_cols(), config.n_mines())
+    }
+}
+
+impl From<GameResult> for GameRecord {
+    fn from(result: GameResult) -> Self {
+        GameRecord {
+            n_rows: result.config.n_rows(),
+            n_cols: result.config.n_cols(),
+            n_mines: result.config.n_mines(),
+            replay: Some(result.replay),
+            won: result.won,
+            elapsed_ms: result.elapsed_ms,
+            clicks: result.clicks.total(),
+            hints: result.hints,
+            three_bv: Some(result.three_bv),
+        }
+    }
+}
+
+/// What the records of one configuration add up to
+#[derive(PartialEq, Default, Debug)]
+pub struct Summary {
+    pub played: usize,
+    pub won: usize,
+    /// Games won in a row up to the last one
+    pub current_streak: usize,
+    pub best_streak: usize,
+    /// Fastest time of a won game played without hints
+    pub best_time_ms: Option<u64>,
+    /// Average time of the won games
+    pub average_time_ms: Option<u64>,
+    /// Highest 3BV per second of a won game played without hints
+    pub best_three_bv_per_second: Option<f64>,
+    /// Average efficiency of the won games, see `crate::game::efficiency`
+    pub average_efficiency: Option<f64>,
+}
+
+impl Summary {
+    /// Returns the share of games won, between 0 and 1, once a game was played
+    pub fn win_rate(&self) -> Option<f64> {
+        if self.played == 0 {
+            return None;
+        }
+        Some(self.won as f64 / self.played as f64)
+    }
+}
+
+/// Every finished game, oldest first
+#[derive(Eq, PartialEq, Default, Debug)]
+pub struct Statistics {
+    records: Vec<GameRecord>,
+}
+
+/// The statistics as stored, next to their version
+#[derive(Serialize, Deserialize)]
+struct StoredRecords<T> {
+    records: T,
+}
+
+impl Statistics {
+    pub fn record(&mut self, result: GameResult) {
+        self.records.push(result.into());
+    }
+
+    pub fn records(&self) -> &[GameRecord] {
+        &self.records
+    }
+
+    pub fn summary(&self, config: GameConfig) -> Summary {
+        let mut summary = Summary::default();
+        let mut total_time_ms = 0;
+        let mut efficiencies = Vec::new();
+        for record in self.records.iter().filter(|r| r.is_played_with(config)) {
+            summary.played += 1;
+            if record.won {
+                summary.won += 1;
+                summary.current_streak += 1;
+                summary.best_streak = summary.best_streak.max(summary.current_streak);
+                total_time_ms += record.elapsed_ms;
+                if record.hints == 0 {
+                    summary.best_time_ms = Some(
+                        summary
+                            .best_time_ms
+                            .map_or(record.elapsed_ms, |best| best.min(record.elapsed_ms)),
+                    );
+                }
+                // records older than version 3 have no 3BV to measure
+                if let Some(three_bv) = record.three_bv {
+                    if record.hints == 0 {
+                        let speed = three_bv_per_second(three_bv, record.elapsed_ms);
+                        summary.best_three_bv_per_second = Some(
+                            summary
+                                .best_three_bv_per_second
+                                .map_or(speed, |best| best.max(speed)),
+                        );
+                    }
+                    efficiencies.extend(efficiency(three_bv, record.clicks));
+                }
+            } else {
+                summary.current_streak = 0;
+            }
+        }
+        if summary.won > 0 {
+            summary.average_time_ms = Some(total_time_ms / summary.won as u64);
+        }
+        if !efficiencies.is_empty() {
+            summary.average_efficiency =
+                Some(efficiencies.iter().sum::<f64>() / efficiencies.len() as f64);
+        }
+        summary
+    }
+
+    /// Returns the records as versioned JSON, to be read back by `from_json`
+    pub fn to_json(&self) -> String {
+        write_envelope(
+            STATS_VERSION,
+            StoredRecords {
+                records: &self.records,
+            },
+        )
+    }
+
+    /// Reads records written by `to_json`, rejecting the ones that could not
+    /// have been played
+    pub fn from_json(json: &str) -> Result<Self, LoadError> {
+        let (_, StoredRecords { records }): (u32, StoredRecords<Vec<GameRecord>>) =
+            read_envelope(json, 1..=STATS_VERSION)?;
+        if records
+            .iter()
+            .any(|r| GameConfig::new(r.n_rows, r.n_cols, r.n_mines).is_err())
+        {
+            return Err(LoadError::Inconsistent(
+                "a game was played on a grid that is not playable",
+            ));
+        }
+        Ok(Statistics { records })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::Clicks;
+    use crate::state::GenerationPolicy;
+
+    fn result(config: GameConfig, won: bool, elapsed_ms: u64) -> GameResult {
+        GameResult {
+            won,
+            config,
+            replay: ReplayCode {
+                config,
+                policy: GenerationPolicy::Immediate,
+                seed: 0,
+                start: None,
+            },
+            elapsed_ms,
+            clicks: Clicks {
+                left: 6,
+                right: 3,
+                chord: 1,
+            },
+            hints: 0,
+            three_bv: 20,
+            zini: 15,
+        }
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut stats = Statistics::default();
+        assert_eq!(stats.summary(GameConfig::BEGINNER), Summary::default());
+        assert_eq!(stats.summary(GameConfig::BEGINNER).win_rate(), None);
+        for (won, elapsed_ms) in [
+            (true, 3000),
+            (true, 2000),
+            (false, 500),
+            (true, 4000),
+            (true, 6000),
+            (true, 5000),
+        ] {
+            stats.record(result(GameConfig::BEGINNER, won, elapsed_ms));
+        }
+        stats.record(result(GameConfig::EXPERT, false, 100_000));
+
+        let summary = stats.summary(GameConfig::BEGINNER);
+        assert_eq!(
+            summary,
+            Summary {
+                played: 6,
+                won: 5,
+                current_streak: 3,
+                best_streak: 3,
+                best_time_ms: Some(2000),
+                average_time_ms: Some(4000),
+                best_three_bv_per_second: Some(10.0),
+                average_efficiency: Some(2.0),
+            }
+        );
+        assert_eq!(summary.win_rate(), Some(5.0 / 6.0));
+
+        let expert = stats.summary(GameConfig::EXPERT);
+        assert_eq!(
+            (expert.played, expert.won, expert.best_time_ms),
+            (1, 0, None)
+        );
+        assert_eq!(expert.win_rate(), Some(0.0));
+    }
+
+    #[test]
+    fn test_streak_broken_by_the_last_game() {
+        let mut stats = Statistics::default();
+        for won in [true, true, false] {
+            stats.record(result(GameConfig::INTERMEDIATE, won, 1000));
+        }
+        let summary = stats.summary(GameConfig::INTERMEDIATE);
+        assert_eq!((summary.current_streak, summary.best_streak), (0, 2));
+    }
+
+    #[test]
+    fn test_hinted_wins_have_no_best_time_or_speed() {
+        let mut stats = Statistics::default();
+        let mut hinted = result(GameConfig::BEGINNER, true, 1000);
+        hinted.hints = 2;
+        stats.record(hinted);
+        assert_eq!(stats.summary(GameConfig::BEGINNER).best_time_ms, None);
+        stats.record(result(GameConfig::BEGINNER, true, 3000));
+        let summary = stats.summary(GameConfig::BEGINNER);
+        assert_eq!(summary.best_time_ms, Some(3000));
+        assert_eq!(summary.average_time_ms, Some(2000));
+        assert_eq!(summary.best_three_bv_per_second, Some(20_000.0 / 3000.0));
+        assert_eq!(summary.average_efficiency, Some(2.0));
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let mut stats = Statistics::default();
+        stats.record(result(GameConfig::BEGINNER, true, 1234));
+        stats.record(result(GameConfig::new(5, 5, 5).unwrap(), false, 99));
+        assert_eq!(Statistics::from_json(&stats.to_json()), Ok(stats));
+        let version_1 = "{\"version\":1,\"records\":[{\"n_rows\":9,\"n_cols\":9,\
+            \"n_mines\":10,\"seed\":0,\"won\":true,\"elapsed_ms\":1234,\"clicks\":10}]}";
+        let stats = Statistics::from_json(version_1).unwrap();
+        assert_eq!(stats.records()[0].hints, 0);
+        assert_eq!(stats.records()[0].three_bv, None);
+        assert_eq!(stats.records()[0].replay, None);
+        let summary = stats.summary(GameConfig::BEGINNER);
+        assert_eq!(summary.best_time_ms, Some(1234));
+        assert_eq!(
+            (summary.best_three_bv_per_second, summary.average_efficiency),
+            (None, None)
+        );
+    }
+
+    #[test]
+    fn test_rejected_json() {
+        assert!(matches!(
+            Statistics::from_json("[]"),
+            Err(LoadError::Malformed(_))
+        ));
+        assert_eq!(
+            Statistics::from_json("{\"version\":5,\"records\":[]}"),
+            Err(LoadError::UnsupportedVersion(5))
+        );
+        let mut stats = Statistics::default();
+        stats.record(result(GameConfig::BEGINNER, true, 1234));
+        let unplayable = stats.to_json().replace("\"n_mines\":10", "\"n_mines\":81");
+        assert!(matches!(
+            Statistics::from_json(&unplayable),
+            Err(LoadError::Inconsistent(_))
+        ));
+    }
+}